@@ -0,0 +1,1281 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use prost::Message as _;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    FileDescriptorSet, ServiceDescriptorProto,
+    field_descriptor_proto::{Label as DescriptorLabel, Type},
+    source_code_info::Location,
+};
+
+use crate::{
+    error::Error,
+    lexer::unescape,
+    model::{
+        Comments, Definition, Enum, EnumValue, Extend, FIELD_PRESENCE, Field, Label, Message,
+        Oneof, Proto, RpcMethod, Service,
+    },
+    resolver::{ProtoFile, ProtoSet},
+};
+
+/// Reads a binary `FileDescriptorSet`, as written by `protoc --descriptor_set_out`
+/// or `buf build`.
+pub fn read(path: &Path) -> Result<FileDescriptorSet, Error> {
+    decode(path, &read_bytes(path)?)
+}
+
+/// Loads every file of a `FileDescriptorSet` into a [`ProtoSet`], in the order
+/// they appear in the set. Types keep their fully-qualified, dotted names.
+pub fn load(path: &Path) -> Result<ProtoSet, Error> {
+    let bytes = read_bytes(path)?;
+    let set = decode(path, &bytes)?;
+    // prost_types drops extensions and predates editions, so `google.api.http`,
+    // `edition` and `features` are decoded separately.
+    let annotations =
+        annotations::FileSet::decode(bytes.as_slice()).map_err(|e| Error::Descriptor {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+
+    let files = set
+        .file
+        .iter()
+        .enumerate()
+        .map(|(i, file)| ProtoFile {
+            name: file.name().to_string(),
+            path: PathBuf::from(file.name()),
+            content: String::new(),
+            proto: convert_file(file, annotations.file.get(i)),
+        })
+        .collect();
+    let mut protos = ProtoSet { files };
+
+    // Custom method options can only be named once every file, and so every
+    // `extend` block, is loaded.
+    let mut custom = Vec::new();
+    for (i, file) in annotations.file.iter().enumerate() {
+        for (j, service) in file.service.iter().enumerate() {
+            for (k, method) in service.method.iter().enumerate() {
+                let Some(bytes) = &method.options else {
+                    continue;
+                };
+                let name = set.file[i].service[j].method[k].name();
+                let options = extension_options(&protos, "google.protobuf.MethodOptions", bytes);
+                custom.push((i, j, name.to_string(), options));
+            }
+        }
+    }
+    for (i, j, name, options) in custom {
+        let service = &mut protos.files[i].proto.services[j];
+        if let Some(method) = service.methods.get_mut(&name) {
+            method.options.extend(options);
+        }
+    }
+
+    Ok(protos)
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| Error::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+fn decode(path: &Path, bytes: &[u8]) -> Result<FileDescriptorSet, Error> {
+    FileDescriptorSet::decode(bytes).map_err(|e| Error::Descriptor {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+fn convert_file(file: &FileDescriptorProto, annotations: Option<&annotations::File>) -> Proto {
+    let package = file.package.clone().filter(|p| !p.is_empty());
+    let scope = package.clone().unwrap_or_default();
+    let syntax = match file.syntax() {
+        "proto3" => Syntax::Proto3,
+        "editions" => Syntax::Editions,
+        _ => Syntax::Proto2,
+    };
+    let source = Source::new(file);
+    let messages = annotations.map_or(&[][..], |a| &a.message_type);
+
+    let services = file
+        .service
+        .iter()
+        .enumerate()
+        .map(|(i, service)| {
+            let annotations = annotations.and_then(|a| a.service.get(i));
+            convert_service(service, annotations, &source, &[6, i as i32])
+        })
+        .collect();
+
+    Proto {
+        package,
+        syntax: file
+            .syntax
+            .clone()
+            .filter(|s| !s.is_empty() && syntax != Syntax::Editions),
+        edition: annotations.and_then(annotations::File::edition_name),
+        options: file_options(file, annotations),
+        imports: file.dependency.clone(),
+        public_imports: dependencies(file, &file.public_dependency),
+        weak_imports: dependencies(file, &file.weak_dependency),
+        services,
+        messages: file
+            .message_type
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let features = messages.get(i);
+                convert_message(m, features, &scope, syntax, &source, &[4, i as i32])
+            })
+            .collect(),
+        enums: file
+            .enum_type
+            .iter()
+            .enumerate()
+            .map(|(i, e)| convert_enum(e, &scope, &source, &[5, i as i32]))
+            .collect(),
+        extends: convert_extends(&file.extension, &scope, syntax, &source, &[7]),
+        comments: Comments::default(),
+    }
+}
+
+/// Looks up the imports at `indexes` into the file's dependency list.
+fn dependencies(file: &FileDescriptorProto, indexes: &[i32]) -> Vec<String> {
+    indexes
+        .iter()
+        .filter_map(|&i| file.dependency.get(usize::try_from(i).ok()?))
+        .cloned()
+        .collect()
+}
+
+fn file_options(
+    file: &FileDescriptorProto,
+    annotations: Option<&annotations::File>,
+) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    let features = annotations.and_then(|a| a.options.as_ref()?.features.as_ref());
+    annotations::FeatureSet::insert_into(features, &mut options);
+    let Some(o) = &file.options else {
+        return options;
+    };
+
+    let strings = [
+        ("java_package", &o.java_package),
+        ("java_outer_classname", &o.java_outer_classname),
+        ("go_package", &o.go_package),
+        ("csharp_namespace", &o.csharp_namespace),
+        ("objc_class_prefix", &o.objc_class_prefix),
+        ("php_namespace", &o.php_namespace),
+        ("ruby_package", &o.ruby_package),
+        ("swift_prefix", &o.swift_prefix),
+    ];
+    for (name, value) in strings {
+        if let Some(value) = value {
+            options.insert(name.to_string(), value.clone());
+        }
+    }
+    if let Some(multiple) = o.java_multiple_files {
+        options.insert("java_multiple_files".into(), multiple.to_string());
+    }
+    options
+}
+
+/// Comments kept by `protoc --include_source_info`, looked up by the path
+/// of field numbers and indexes leading to a declaration.
+struct Source<'a> {
+    locations: HashMap<&'a [i32], &'a Location>,
+}
+
+impl<'a> Source<'a> {
+    fn new(file: &'a FileDescriptorProto) -> Self {
+        let locations = file
+            .source_code_info
+            .iter()
+            .flat_map(|info| &info.location)
+            .map(|location| (location.path.as_slice(), location))
+            .collect();
+        Source { locations }
+    }
+
+    fn comments(&self, path: &[i32]) -> Comments {
+        let Some(location) = self.locations.get(path) else {
+            return Comments::default();
+        };
+
+        let mut leading = Vec::new();
+        for detached in &location.leading_detached_comments {
+            leading.extend(line_comments(detached));
+            leading.push(String::new());
+        }
+        leading.extend(
+            location
+                .leading_comments
+                .iter()
+                .flat_map(|c| line_comments(c)),
+        );
+
+        Comments {
+            leading,
+            trailing: location
+                .trailing_comments
+                .as_deref()
+                .and_then(|c| line_comments(c).into_iter().next()),
+        }
+    }
+}
+
+/// Turns comment text, stored by protoc without markers, back into `//` lines.
+fn line_comments(text: &str) -> Vec<String> {
+    text.trim_end_matches('\n')
+        .lines()
+        .map(|line| format!("//{}", line.trim_end()))
+        .collect()
+}
+
+fn child(path: &[i32], field: i32, index: usize) -> Vec<i32> {
+    [path, &[field, index as i32]].concat()
+}
+
+/// Which of proto2, proto3 and Editions a file is written in; each derives
+/// field labels differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Proto2,
+    Proto3,
+    Editions,
+}
+
+fn convert_message(
+    message: &DescriptorProto,
+    features: Option<&annotations::Message>,
+    scope: &str,
+    syntax: Syntax,
+    source: &Source,
+    path: &[i32],
+) -> Message {
+    let full_name = qualify(scope, message.name());
+
+    // Maps are encoded as repeated fields of a nested `XxxEntry` message.
+    let map_entries: HashMap<String, &DescriptorProto> = message
+        .nested_type
+        .iter()
+        .filter(|n| n.options.as_ref().is_some_and(|o| o.map_entry()))
+        .map(|n| (format!(".{}.{}", full_name, n.name()), n))
+        .collect();
+
+    let mut fields = Vec::new();
+    let mut oneofs: Vec<Oneof> = message
+        .oneof_decl
+        .iter()
+        .enumerate()
+        .map(|(i, o)| Oneof {
+            name: o.name().to_string(),
+            fields: Vec::new(),
+            comments: source.comments(&child(path, 8, i)),
+            span: Default::default(),
+        })
+        .collect();
+    for (i, field) in message.field.iter().enumerate() {
+        let comments = source.comments(&child(path, 2, i));
+        let features = features.and_then(|f| f.field.get(i));
+        let converted = convert_field(field, features, &map_entries, syntax, comments);
+        // proto3 `optional` fields sit in a synthetic oneof of their own.
+        match field.oneof_index {
+            Some(i) if !field.proto3_optional() => match oneofs.get_mut(i as usize) {
+                Some(oneof) => oneof.fields.push(converted),
+                None => fields.push(converted),
+            },
+            _ => fields.push(converted),
+        }
+    }
+    oneofs.retain(|o| !o.fields.is_empty());
+
+    let mut options = HashMap::new();
+    if message.options.as_ref().is_some_and(|o| o.deprecated()) {
+        options.insert("deprecated".to_string(), "true".to_string());
+    }
+    let message_features = features.and_then(|f| f.options.as_ref()?.features.as_ref());
+    annotations::FeatureSet::insert_into(message_features, &mut options);
+
+    Message {
+        fields,
+        oneofs,
+        messages: message
+            .nested_type
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| !n.options.as_ref().is_some_and(|o| o.map_entry()))
+            .map(|(i, n)| {
+                let features = features.and_then(|f| f.nested_type.get(i));
+                convert_message(n, features, &full_name, syntax, source, &child(path, 3, i))
+            })
+            .collect(),
+        enums: message
+            .enum_type
+            .iter()
+            .enumerate()
+            .map(|(i, e)| convert_enum(e, &full_name, source, &child(path, 4, i)))
+            .collect(),
+        options,
+        // Descriptor ranges are end-exclusive.
+        reserved_numbers: message
+            .reserved_range
+            .iter()
+            .map(|r| r.start() as u32..=(r.end() - 1) as u32)
+            .collect(),
+        reserved_names: message.reserved_name.clone(),
+        extensions: message
+            .extension_range
+            .iter()
+            .map(|r| r.start() as u32..=(r.end() - 1) as u32)
+            .collect(),
+        extends: convert_extends(
+            &message.extension,
+            &full_name,
+            syntax,
+            source,
+            &[path, &[6]].concat(),
+        ),
+        name: message.name().to_string(),
+        full_name,
+        comments: source.comments(path),
+        span: Default::default(),
+    }
+}
+
+fn convert_field(
+    field: &FieldDescriptorProto,
+    features: Option<&annotations::Field>,
+    map_entries: &HashMap<String, &DescriptorProto>,
+    syntax: Syntax,
+    comments: Comments,
+) -> Field {
+    let mut options = HashMap::new();
+    if let Some(json_name) = &field.json_name
+        && *json_name != default_json_name(field.name())
+    {
+        options.insert("json_name".to_string(), json_name.clone());
+    }
+    if field.options.as_ref().is_some_and(|o| o.deprecated()) {
+        options.insert("deprecated".to_string(), "true".to_string());
+    }
+    if let Some(default) = &field.default_value {
+        // Bytes defaults are stored C-escaped; the model holds them decoded.
+        let default = match field.r#type() {
+            Type::Bytes => unescape(&format!("\"{}\"", default)).unwrap_or(default.clone()),
+            _ => default.clone(),
+        };
+        options.insert("default".to_string(), default);
+    }
+    let features = features.and_then(|f| f.options.as_ref()?.features.as_ref());
+    annotations::FeatureSet::insert_into(features, &mut options);
+
+    let entry = map_entries.get(field.type_name());
+    let (field_type, key_type) = match entry {
+        Some(entry) => {
+            let key = entry.field.iter().find(|f| f.number() == 1);
+            let value = entry.field.iter().find(|f| f.number() == 2);
+            (value.map(type_name).unwrap_or_default(), key.map(type_name))
+        }
+        None => (type_name(field), None),
+    };
+
+    let repeated = field.label() == DescriptorLabel::Repeated && entry.is_none();
+    let label = match field.label() {
+        // Editions have no labels; presence comes from features alone.
+        DescriptorLabel::Required if syntax == Syntax::Editions => {
+            options
+                .entry(FIELD_PRESENCE.to_string())
+                .or_insert_with(|| "LEGACY_REQUIRED".to_string());
+            None
+        }
+        _ if syntax == Syntax::Editions => None,
+        DescriptorLabel::Required => Some(Label::Required),
+        DescriptorLabel::Optional if field.proto3_optional() => Some(Label::Optional),
+        // Every singular proto2 field outside a oneof carries an explicit label.
+        DescriptorLabel::Optional if syntax == Syntax::Proto2 && field.oneof_index.is_none() => {
+            Some(Label::Optional)
+        }
+        _ => None,
+    };
+
+    Field {
+        name: field.name().to_string(),
+        field_type,
+        number: field.number() as u32,
+        repeated,
+        label,
+        key_type,
+        options,
+        group: field.r#type() == Type::Group,
+        comments,
+        span: Default::default(),
+    }
+}
+
+/// Groups extension fields, found at `path` in the source info, into one
+/// `extend` block per run of fields with the same extendee.
+fn convert_extends(
+    fields: &[FieldDescriptorProto],
+    scope: &str,
+    syntax: Syntax,
+    source: &Source,
+    path: &[i32],
+) -> Vec<Extend> {
+    let mut extends: Vec<Extend> = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let comments = source.comments(&[path, &[i as i32]].concat());
+        let converted = convert_field(field, None, &HashMap::new(), syntax, comments);
+        match extends.last_mut() {
+            Some(extend) if extend.extendee == field.extendee() => extend.fields.push(converted),
+            _ => extends.push(Extend {
+                extendee: field.extendee().to_string(),
+                scope: scope.to_string(),
+                fields: vec![converted],
+                comments: Comments::default(),
+                span: Default::default(),
+            }),
+        }
+    }
+    extends
+}
+
+/// The proto name of a field's type; messages and enums keep the leading dot.
+fn type_name(field: &FieldDescriptorProto) -> String {
+    let name = match field.r#type() {
+        Type::Double => "double",
+        Type::Float => "float",
+        Type::Int64 => "int64",
+        Type::Uint64 => "uint64",
+        Type::Int32 => "int32",
+        Type::Fixed64 => "fixed64",
+        Type::Fixed32 => "fixed32",
+        Type::Bool => "bool",
+        Type::String => "string",
+        Type::Bytes => "bytes",
+        Type::Uint32 => "uint32",
+        Type::Sfixed32 => "sfixed32",
+        Type::Sfixed64 => "sfixed64",
+        Type::Sint32 => "sint32",
+        Type::Sint64 => "sint64",
+        Type::Group | Type::Message | Type::Enum => field.type_name(),
+    };
+    name.to_string()
+}
+
+/// The JSON name protoc derives for a field: lowerCamelCase of its name.
+fn default_json_name(name: &str) -> String {
+    let mut json = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            json.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            json.push(c);
+        }
+    }
+    json
+}
+
+fn convert_enum(e: &EnumDescriptorProto, scope: &str, source: &Source, path: &[i32]) -> Enum {
+    let mut options = HashMap::new();
+    if e.options.as_ref().is_some_and(|o| o.deprecated()) {
+        options.insert("deprecated".to_string(), "true".to_string());
+    }
+
+    Enum {
+        name: e.name().to_string(),
+        full_name: qualify(scope, e.name()),
+        values: e
+            .value
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let mut options = HashMap::new();
+                if v.options.as_ref().is_some_and(|o| o.deprecated()) {
+                    options.insert("deprecated".to_string(), "true".to_string());
+                }
+                EnumValue {
+                    name: v.name().to_string(),
+                    number: v.number(),
+                    options,
+                    comments: source.comments(&child(path, 2, i)),
+                }
+            })
+            .collect(),
+        allow_alias: e.options.as_ref().is_some_and(|o| o.allow_alias()),
+        options,
+        // Unlike message ranges, enum ranges are end-inclusive.
+        reserved_numbers: e
+            .reserved_range
+            .iter()
+            .map(|r| r.start()..=r.end())
+            .collect(),
+        reserved_names: e.reserved_name.clone(),
+        comments: source.comments(path),
+        span: Default::default(),
+    }
+}
+
+fn convert_service(
+    service: &ServiceDescriptorProto,
+    annotations: Option<&annotations::Service>,
+    source: &Source,
+    path: &[i32],
+) -> Service {
+    let methods = service
+        .method
+        .iter()
+        .enumerate()
+        .map(|(i, method)| {
+            let http = annotations
+                .and_then(|a| a.method.get(i))
+                .and_then(annotations::Method::http);
+
+            let mut options = HashMap::new();
+            if method.options.as_ref().is_some_and(|o| o.deprecated()) {
+                options.insert("deprecated".to_string(), "true".to_string());
+            }
+
+            let method = RpcMethod {
+                name: method.name().to_string(),
+                request: method.input_type().to_string(),
+                response: method.output_type().to_string(),
+                client_streaming: method.client_streaming(),
+                server_streaming: method.server_streaming(),
+                options,
+                http,
+                comments: source.comments(&child(path, 2, i)),
+                span: Default::default(),
+            };
+            (method.name.clone(), method)
+        })
+        .collect();
+
+    Service {
+        name: service.name().to_string(),
+        methods,
+        comments: source.comments(path),
+        span: Default::default(),
+    }
+}
+
+/// Decodes the extension fields set in `bytes`, an encoded `extendee`
+/// message, as options written the way source does: `(.acme.auth.required)`
+/// for a scalar extension and `(.acme.auth).required` for each field of a
+/// message-typed one. Extensions not declared in `protos` are skipped.
+fn extension_options(protos: &ProtoSet, extendee: &str, bytes: &[u8]) -> Vec<(String, String)> {
+    let extends: Vec<&Extend> = protos
+        .files
+        .iter()
+        .flat_map(|f| f.proto.all_extends())
+        .filter(|extend| extend.extendee.trim_start_matches('.') == extendee)
+        .collect();
+
+    let mut options = Vec::new();
+    for (number, value) in wire_fields(bytes) {
+        let declared = extends.iter().find_map(|extend| {
+            let field = extend.fields.iter().find(|f| f.number == number)?;
+            Some((extend, field))
+        });
+        if let Some((extend, field)) = declared {
+            let name = format!("(.{})", extend.full_name(field));
+            option_values(protos, field, value, &name, &mut options);
+        }
+    }
+    options
+}
+
+/// A field value as read off the wire.
+#[derive(Clone, Copy)]
+enum WireValue<'a> {
+    Varint(u64),
+    Fixed32(u32),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Splits an encoded message into its field numbers and values, stopping at
+/// groups or malformed input.
+fn wire_fields(mut bytes: &[u8]) -> Vec<(u32, WireValue<'_>)> {
+    use prost::encoding::{WireType, decode_key, decode_varint};
+
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let Ok((number, wire_type)) = decode_key(&mut bytes) else {
+            break;
+        };
+        let value = match wire_type {
+            WireType::Varint => match decode_varint(&mut bytes) {
+                Ok(value) => WireValue::Varint(value),
+                Err(_) => break,
+            },
+            WireType::ThirtyTwoBit if bytes.len() >= 4 => {
+                let (value, rest) = bytes.split_at(4);
+                bytes = rest;
+                WireValue::Fixed32(u32::from_le_bytes(value.try_into().unwrap()))
+            }
+            WireType::SixtyFourBit if bytes.len() >= 8 => {
+                let (value, rest) = bytes.split_at(8);
+                bytes = rest;
+                WireValue::Fixed64(u64::from_le_bytes(value.try_into().unwrap()))
+            }
+            WireType::LengthDelimited => {
+                let Some(len) = decode_varint(&mut bytes)
+                    .ok()
+                    .and_then(|len| usize::try_from(len).ok())
+                    .filter(|len| *len <= bytes.len())
+                else {
+                    break;
+                };
+                let (value, rest) = bytes.split_at(len);
+                bytes = rest;
+                WireValue::Bytes(value)
+            }
+            _ => break,
+        };
+        fields.push((number, value));
+    }
+    fields
+}
+
+/// Adds the option `name` for `value`, read for `field`. A message value adds
+/// one option per field set in it, named by appending the field's name.
+fn option_values(
+    protos: &ProtoSet,
+    field: &Field,
+    value: WireValue,
+    name: &str,
+    options: &mut Vec<(String, String)>,
+) {
+    use WireValue::*;
+
+    let text = match (field.field_type.as_str(), value) {
+        ("bool", Varint(v)) => (v != 0).to_string(),
+        ("int32" | "int64", Varint(v)) => (v as i64).to_string(),
+        ("uint32" | "uint64", Varint(v)) => v.to_string(),
+        ("sint32" | "sint64", Varint(v)) => ((v >> 1) as i64 ^ -((v & 1) as i64)).to_string(),
+        ("fixed32", Fixed32(v)) => v.to_string(),
+        ("sfixed32", Fixed32(v)) => (v as i32).to_string(),
+        ("float", Fixed32(v)) => f32::from_bits(v).to_string(),
+        ("fixed64", Fixed64(v)) => v.to_string(),
+        ("sfixed64", Fixed64(v)) => (v as i64).to_string(),
+        ("double", Fixed64(v)) => f64::from_bits(v).to_string(),
+        ("string" | "bytes", Bytes(b)) => String::from_utf8_lossy(b).into_owned(),
+        (type_name, value) => match (protos.find(type_name.trim_start_matches('.')), value) {
+            (Some(Definition::Enum(e)), Varint(v)) => e
+                .values
+                .iter()
+                .find(|ev| i64::from(ev.number) == v as i64)
+                .map_or_else(|| (v as i64).to_string(), |ev| ev.name.clone()),
+            (Some(Definition::Message(message)), Bytes(b)) => {
+                let fields: Vec<&Field> = message
+                    .fields
+                    .iter()
+                    .chain(message.oneofs.iter().flat_map(|o| &o.fields))
+                    .collect();
+                for (number, value) in wire_fields(b) {
+                    if let Some(field) = fields.iter().find(|f| f.number == number) {
+                        let name = format!("{}.{}", name, field.name);
+                        option_values(protos, field, value, &name, options);
+                    }
+                }
+                return;
+            }
+            _ => return,
+        },
+    };
+    options.push((name.to_string(), text));
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// Just enough of descriptor.proto to reach what `prost_types` has no field
+/// for: the `google.api.http` method option, and the `edition` and
+/// `field_presence` feature of Editions files.
+mod annotations {
+    use std::collections::HashMap;
+
+    use prost::Message as _;
+
+    use crate::model;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FileSet {
+        #[prost(message, repeated, tag = "1")]
+        pub file: Vec<File>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct File {
+        #[prost(message, repeated, tag = "4")]
+        pub message_type: Vec<Message>,
+        #[prost(message, repeated, tag = "6")]
+        pub service: Vec<Service>,
+        #[prost(message, optional, tag = "8")]
+        pub options: Option<FileOptions>,
+        #[prost(int32, optional, tag = "14")]
+        pub edition: Option<i32>,
+    }
+
+    impl File {
+        /// The edition as written in source, such as `2023`.
+        pub fn edition_name(&self) -> Option<String> {
+            match self.edition? {
+                // EDITION_UNKNOWN, EDITION_PROTO2 and EDITION_PROTO3.
+                0 | 998 | 999 => None,
+                1000 => Some("2023".into()),
+                1001 => Some("2024".into()),
+                other => Some(other.to_string()),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FileOptions {
+        #[prost(message, optional, tag = "50")]
+        pub features: Option<FeatureSet>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Message {
+        #[prost(message, repeated, tag = "2")]
+        pub field: Vec<Field>,
+        #[prost(message, repeated, tag = "3")]
+        pub nested_type: Vec<Message>,
+        #[prost(message, optional, tag = "7")]
+        pub options: Option<MessageOptions>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MessageOptions {
+        #[prost(message, optional, tag = "12")]
+        pub features: Option<FeatureSet>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Field {
+        #[prost(message, optional, tag = "8")]
+        pub options: Option<FieldOptions>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FieldOptions {
+        #[prost(message, optional, tag = "21")]
+        pub features: Option<FeatureSet>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FeatureSet {
+        #[prost(int32, optional, tag = "1")]
+        pub field_presence: Option<i32>,
+    }
+
+    impl FeatureSet {
+        /// Records the features set here as options, the way they are
+        /// written in source.
+        pub fn insert_into(features: Option<&FeatureSet>, options: &mut HashMap<String, String>) {
+            let presence = match features.and_then(|f| f.field_presence) {
+                Some(1) => "EXPLICIT",
+                Some(2) => "IMPLICIT",
+                Some(3) => "LEGACY_REQUIRED",
+                _ => return,
+            };
+            options.insert(model::FIELD_PRESENCE.to_string(), presence.to_string());
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Service {
+        #[prost(message, repeated, tag = "2")]
+        pub method: Vec<Method>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Method {
+        /// Kept encoded, since custom options are extension fields that only
+        /// the `extend` declarations in the set can name.
+        #[prost(bytes = "vec", optional, tag = "4")]
+        pub options: Option<Vec<u8>>,
+    }
+
+    impl Method {
+        pub fn http(&self) -> Option<model::HttpRule> {
+            let options = MethodOptions::decode(self.options.as_deref()?).ok()?;
+            options.http.as_ref()?.to_model()
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MethodOptions {
+        #[prost(message, optional, tag = "72295728")]
+        pub http: Option<HttpRule>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HttpRule {
+        #[prost(string, optional, tag = "2")]
+        pub get: Option<String>,
+        #[prost(string, optional, tag = "3")]
+        pub put: Option<String>,
+        #[prost(string, optional, tag = "4")]
+        pub post: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub delete: Option<String>,
+        #[prost(string, optional, tag = "6")]
+        pub patch: Option<String>,
+        #[prost(string, tag = "7")]
+        pub body: String,
+        #[prost(message, optional, tag = "8")]
+        pub custom: Option<CustomPattern>,
+        #[prost(message, repeated, tag = "11")]
+        pub additional_bindings: Vec<HttpRule>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CustomPattern {
+        #[prost(string, tag = "1")]
+        pub kind: String,
+        #[prost(string, tag = "2")]
+        pub path: String,
+    }
+
+    impl HttpRule {
+        pub fn to_model(&self) -> Option<model::HttpRule> {
+            let (method, path) = [
+                ("GET", &self.get),
+                ("PUT", &self.put),
+                ("POST", &self.post),
+                ("DELETE", &self.delete),
+                ("PATCH", &self.patch),
+            ]
+            .into_iter()
+            .find_map(|(method, path)| Some((method.to_string(), path.clone()?)))
+            .or_else(|| {
+                let custom = self.custom.as_ref()?;
+                Some((custom.kind.to_uppercase(), custom.path.clone()))
+            })?;
+
+            Some(model::HttpRule {
+                method,
+                path,
+                body: Some(self.body.clone()).filter(|b| !b.is_empty()),
+                additional_bindings: self
+                    .additional_bindings
+                    .iter()
+                    .filter_map(HttpRule::to_model)
+                    .collect(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::encoding::{WireType, encode_key, encode_varint};
+    use prost_types::{
+        EnumValueDescriptorProto, FieldOptions, FileOptions, MessageOptions, MethodDescriptorProto,
+        OneofDescriptorProto, SourceCodeInfo, descriptor_proto::ReservedRange,
+    };
+
+    use super::*;
+    use crate::model::FieldPresence;
+
+    fn field(
+        name: &str,
+        number: i32,
+        label: DescriptorLabel,
+        kind: Type,
+        type_name: Option<&str>,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.into()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(kind as i32),
+            type_name: type_name.map(Into::into),
+            json_name: Some(default_json_name(name)),
+            ..Default::default()
+        }
+    }
+
+    /// Encodes `bytes` as length-delimited field `tag`.
+    fn nested(tag: u32, bytes: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_key(tag, WireType::LengthDelimited, &mut buf);
+        encode_varint(bytes.len() as u64, &mut buf);
+        buf.extend_from_slice(bytes);
+        buf
+    }
+
+    fn shop() -> Vec<u8> {
+        let mut item_id = field("item_id", 1, DescriptorLabel::Optional, Type::String, None);
+        item_id.json_name = Some("sku".into());
+        item_id.options = Some(FieldOptions {
+            deprecated: Some(true),
+            ..Default::default()
+        });
+        let mut note = field("note", 4, DescriptorLabel::Optional, Type::String, None);
+        note.proto3_optional = Some(true);
+        note.oneof_index = Some(1);
+        let mut card = field("card", 5, DescriptorLabel::Optional, Type::String, None);
+        card.oneof_index = Some(0);
+
+        let order = DescriptorProto {
+            name: Some("Order".into()),
+            field: vec![
+                item_id,
+                field(
+                    "labels",
+                    2,
+                    DescriptorLabel::Repeated,
+                    Type::Message,
+                    Some(".shop.Order.LabelsEntry"),
+                ),
+                field(
+                    "status",
+                    3,
+                    DescriptorLabel::Optional,
+                    Type::Enum,
+                    Some(".shop.Status"),
+                ),
+                note,
+                card,
+                field("tags", 6, DescriptorLabel::Repeated, Type::String, None),
+            ],
+            nested_type: vec![DescriptorProto {
+                name: Some("LabelsEntry".into()),
+                field: vec![
+                    field("key", 1, DescriptorLabel::Optional, Type::String, None),
+                    field("value", 2, DescriptorLabel::Optional, Type::Int64, None),
+                ],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            oneof_decl: vec![
+                OneofDescriptorProto {
+                    name: Some("payment".into()),
+                    ..Default::default()
+                },
+                OneofDescriptorProto {
+                    name: Some("_note".into()),
+                    ..Default::default()
+                },
+            ],
+            reserved_range: vec![ReservedRange {
+                start: Some(9),
+                end: Some(12),
+            }],
+            reserved_name: vec!["legacy".into()],
+            ..Default::default()
+        };
+
+        let file = FileDescriptorProto {
+            name: Some("shop/v1/shop.proto".into()),
+            package: Some("shop".into()),
+            dependency: vec![
+                "google/api/annotations.proto".into(),
+                "shop/v1/common.proto".into(),
+            ],
+            public_dependency: vec![1],
+            message_type: vec![
+                order,
+                DescriptorProto {
+                    name: Some("Policy".into()),
+                    field: vec![
+                        field("role", 1, DescriptorLabel::Optional, Type::String, None),
+                        field(
+                            "status",
+                            2,
+                            DescriptorLabel::Optional,
+                            Type::Enum,
+                            Some(".shop.Status"),
+                        ),
+                        field("retries", 3, DescriptorLabel::Optional, Type::Sint32, None),
+                    ],
+                    ..Default::default()
+                },
+            ],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Status".into()),
+                value: vec![
+                    EnumValueDescriptorProto {
+                        name: Some("STATUS_UNSPECIFIED".into()),
+                        number: Some(0),
+                        ..Default::default()
+                    },
+                    EnumValueDescriptorProto {
+                        name: Some("STATUS_OPEN".into()),
+                        number: Some(1),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            extension: vec![
+                FieldDescriptorProto {
+                    extendee: Some(".google.protobuf.MethodOptions".into()),
+                    ..field("audit", 50001, DescriptorLabel::Optional, Type::Bool, None)
+                },
+                FieldDescriptorProto {
+                    extendee: Some(".google.protobuf.MethodOptions".into()),
+                    ..field(
+                        "policy",
+                        50002,
+                        DescriptorLabel::Optional,
+                        Type::Message,
+                        Some(".shop.Policy"),
+                    )
+                },
+            ],
+            options: Some(FileOptions {
+                java_package: Some("com.shop".into()),
+                ..Default::default()
+            }),
+            syntax: Some("proto3".into()),
+            source_code_info: Some(SourceCodeInfo {
+                location: vec![
+                    Location {
+                        path: vec![4, 0],
+                        leading_comments: Some(" An order.\n Placed by a customer.\n".into()),
+                        leading_detached_comments: vec![" Orders.\n".into()],
+                        ..Default::default()
+                    },
+                    Location {
+                        path: vec![4, 0, 2, 0],
+                        trailing_comments: Some(" the item\n".into()),
+                        ..Default::default()
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let service = ServiceDescriptorProto {
+            name: Some("Orders".into()),
+            ..Default::default()
+        };
+        let method = MethodDescriptorProto {
+            name: Some("Create".into()),
+            input_type: Some(".shop.Order".into()),
+            output_type: Some(".shop.Order".into()),
+            ..Default::default()
+        };
+        let options = annotations::MethodOptions {
+            http: Some(annotations::HttpRule {
+                post: Some("/v1/orders".into()),
+                body: "*".into(),
+                additional_bindings: vec![annotations::HttpRule {
+                    custom: Some(annotations::CustomPattern {
+                        kind: "head".into(),
+                        path: "/v1/orders".into(),
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        };
+
+        // Custom options: `(shop.audit) = true` and
+        // `(shop.policy) = { role: "admin" status: STATUS_OPEN retries: -2 }`.
+        let mut audit = Vec::new();
+        encode_key(50001, WireType::Varint, &mut audit);
+        encode_varint(1, &mut audit);
+        let mut policy = nested(1, b"admin");
+        encode_key(2, WireType::Varint, &mut policy);
+        encode_varint(1, &mut policy);
+        encode_key(3, WireType::Varint, &mut policy);
+        encode_varint(3, &mut policy);
+        let options = [options.encode_to_vec(), audit, nested(50002, &policy)].concat();
+
+        // MethodOptions in prost_types cannot carry extensions, so the service
+        // is appended to the file by hand.
+        let method = [method.encode_to_vec(), nested(4, &options)].concat();
+        let service = [service.encode_to_vec(), nested(2, &method)].concat();
+        let file = [file.encode_to_vec(), nested(6, &service)].concat();
+        nested(1, &file)
+    }
+
+    fn editions() -> Vec<u8> {
+        let features = |presence| annotations::FeatureSet {
+            field_presence: Some(presence),
+        };
+        let options = annotations::FieldOptions {
+            features: Some(features(1)),
+        };
+        let note = field("note", 3, DescriptorLabel::Optional, Type::String, None);
+        let note = [note.encode_to_vec(), nested(8, &options.encode_to_vec())].concat();
+
+        let order = DescriptorProto {
+            name: Some("Order".into()),
+            field: vec![
+                field("id", 1, DescriptorLabel::Optional, Type::String, None),
+                field("sku", 2, DescriptorLabel::Required, Type::String, None),
+            ],
+            ..Default::default()
+        };
+        let order = [order.encode_to_vec(), nested(2, &note)].concat();
+
+        let file = FileDescriptorProto {
+            name: Some("shop/v2/shop.proto".into()),
+            package: Some("shop".into()),
+            syntax: Some("editions".into()),
+            ..Default::default()
+        };
+        let options = annotations::FileOptions {
+            features: Some(features(2)),
+        };
+        // Neither `edition` nor features exist in prost_types, so they are
+        // appended by hand.
+        let mut edition = Vec::new();
+        encode_key(14, WireType::Varint, &mut edition);
+        encode_varint(1000, &mut edition);
+        let file = [
+            file.encode_to_vec(),
+            nested(4, &order),
+            nested(8, &options.encode_to_vec()),
+            edition,
+        ]
+        .concat();
+        nested(1, &file)
+    }
+
+    fn write(name: &str, bytes: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("proto_def_{}_{}.pb", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_descriptor_set() {
+        let path = write("descriptor", &shop());
+        let protos = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(protos.files.len(), 1);
+        assert_eq!(protos.files[0].name, "shop/v1/shop.proto");
+        let proto = protos.root();
+        assert_eq!(proto.package.as_deref(), Some("shop"));
+        assert_eq!(proto.syntax.as_deref(), Some("proto3"));
+        assert_eq!(
+            proto.imports,
+            vec!["google/api/annotations.proto", "shop/v1/common.proto"]
+        );
+        assert_eq!(proto.public_imports, vec!["shop/v1/common.proto"]);
+        assert!(proto.weak_imports.is_empty());
+        assert_eq!(proto.options["java_package"], "com.shop");
+
+        let order = &proto.messages[0];
+        assert_eq!(order.full_name, "shop.Order");
+        assert!(order.messages.is_empty());
+        assert_eq!(order.reserved_numbers, vec![9..=11]);
+        assert_eq!(order.reserved_names, vec!["legacy"]);
+        assert_eq!(
+            order.comments.doc().as_deref(),
+            Some("An order.\nPlaced by a customer.")
+        );
+        assert_eq!(order.comments.leading[..2], ["// Orders.", ""]);
+
+        let names: Vec<_> = order.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["item_id", "labels", "status", "note", "tags"]);
+        let item_id = &order.fields[0];
+        assert_eq!(item_id.json_name(), Some("sku"));
+        assert!(item_id.deprecated());
+        assert_eq!(item_id.comments.trailing.as_deref(), Some("// the item"));
+        let labels = &order.fields[1];
+        assert_eq!(labels.key_type.as_deref(), Some("string"));
+        assert_eq!(labels.field_type, "int64");
+        assert!(!labels.repeated);
+        assert_eq!(order.fields[2].field_type, ".shop.Status");
+        assert_eq!(order.fields[3].label, Some(Label::Optional));
+        assert!(order.fields[4].repeated);
+        assert_eq!(order.fields[4].label, None);
+
+        assert_eq!(order.oneofs.len(), 1);
+        assert_eq!(order.oneofs[0].name, "payment");
+        assert_eq!(order.oneofs[0].fields[0].name, "card");
+
+        let method = &proto.services[0].methods["Create"];
+        assert_eq!(method.request, ".shop.Order");
+        assert!(
+            protos
+                .resolve_message("shop.Orders", &method.request)
+                .is_some()
+        );
+        let http = method.http.as_ref().unwrap();
+        assert_eq!(
+            (http.method.as_str(), http.path.as_str()),
+            ("POST", "/v1/orders")
+        );
+        assert_eq!(http.body.as_deref(), Some("*"));
+        assert_eq!(http.additional_bindings[0].method, "HEAD");
+
+        let custom = protos.custom_options("shop.Orders", method);
+        let mut custom: Vec<_> = custom.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        custom.sort();
+        assert_eq!(
+            custom,
+            [
+                ("shop.audit", "true"),
+                ("shop.policy.retries", "-2"),
+                ("shop.policy.role", "admin"),
+                ("shop.policy.status", "STATUS_OPEN"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_editions_descriptor_set() {
+        let path = write("editions", &editions());
+        let protos = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let proto = protos.root();
+        assert_eq!(proto.edition.as_deref(), Some("2023"));
+        assert_eq!(proto.syntax, None);
+        assert_eq!(proto.options[FIELD_PRESENCE], "IMPLICIT");
+
+        let order = &proto.messages[0];
+        assert!(order.fields.iter().all(|f| f.label.is_none()));
+        let presence: Vec<_> = order
+            .fields
+            .iter()
+            .map(|f| proto.field_presence("shop.Order", f))
+            .collect();
+        assert_eq!(
+            presence,
+            vec![
+                FieldPresence::Implicit,
+                FieldPresence::LegacyRequired,
+                FieldPresence::Explicit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_descriptor_set() {
+        let path = write("invalid", b"not a descriptor");
+        let error = load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(error, Error::Descriptor { .. }));
+    }
+}
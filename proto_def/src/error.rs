@@ -0,0 +1,168 @@
+use std::{fmt, path::PathBuf};
+
+/// A byte range in the source, with the 1-based line and column of its start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Input that is not a valid token.
+    Lex { span: Span, text: String },
+    /// Tokens that do not form a valid proto file.
+    Parse { span: Span, message: String },
+    /// A proto file that could not be read.
+    Io { path: PathBuf, message: String },
+    /// A compiled descriptor set that could not be decoded.
+    Descriptor { path: PathBuf, message: String },
+    /// An import missing from every include path.
+    Import {
+        name: String,
+        include_paths: Vec<PathBuf>,
+    },
+    /// An error raised while processing `path`, kept with the file's content
+    /// so it can be rendered.
+    File {
+        path: PathBuf,
+        content: String,
+        error: Box<Error>,
+    },
+    /// Several errors, reported together.
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Lex { span, .. } | Error::Parse { span, .. } => Some(*span),
+            Error::File { error, .. } => error.span(),
+            Error::Io { .. }
+            | Error::Descriptor { .. }
+            | Error::Import { .. }
+            | Error::Multiple(_) => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Error::Lex { text, .. } => format!("unexpected character sequence '{}'", text),
+            Error::Parse { message, .. } => message.clone(),
+            Error::Io { path, message } => {
+                format!("could not read '{}': {}", path.display(), message)
+            }
+            Error::Descriptor { path, message } => format!(
+                "'{}' is not a valid FileDescriptorSet: {}",
+                path.display(),
+                message
+            ),
+            Error::Import {
+                name,
+                include_paths,
+            } => format!(
+                "import '{}' not found in include paths {:?}",
+                name, include_paths
+            ),
+            Error::File { error, .. } => error.message(),
+            Error::Multiple(errors) => format!("{} errors", errors.len()),
+        }
+    }
+
+    /// Renders the error against `content`, showing the offending line with a
+    /// caret under the span:
+    ///
+    /// ```text
+    /// error: Expected Semicolon, found RightBrace
+    ///  --> shop.proto:3:1
+    ///   |
+    /// 3 | }
+    ///   | ^
+    /// ```
+    pub fn render(&self, path: &str, content: &str) -> String {
+        if let Error::Multiple(errors) = self {
+            let rendered: Vec<_> = errors.iter().map(|e| e.render(path, content)).collect();
+            return rendered.join("\n\n");
+        }
+        let Some(span) = self.span() else {
+            return format!("error: {}", self.message());
+        };
+
+        let line = content.lines().nth(span.line - 1).unwrap_or_default();
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let width = content[span.start..span.end.min(content.len())]
+            .lines()
+            .next()
+            .map_or(1, |text| text.chars().count().max(1));
+
+        format!(
+            "error: {}\n{} --> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self.message(),
+            gutter,
+            path,
+            span.line,
+            span.column,
+            gutter,
+            number,
+            line,
+            gutter,
+            " ".repeat(span.column - 1),
+            "^".repeat(width),
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::File {
+                path,
+                content,
+                error,
+            } => write!(f, "{}", error.render(&path.display().to_string(), content)),
+            Error::Multiple(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\n\n")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            _ => match self.span() {
+                Some(span) => write!(f, "{} at {}:{}", self.message(), span.line, span.column),
+                None => write!(f, "{}", self.message()),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_points_at_span() {
+        let content = "syntax = \"proto3\";\nmessage M {\n  string name = 1\n}\n";
+        let error = Error::Parse {
+            span: Span {
+                start: 49,
+                end: 50,
+                line: 4,
+                column: 1,
+            },
+            message: "Expected Semicolon, found RightBrace".into(),
+        };
+
+        assert_eq!(
+            error.render("m.proto", content),
+            "error: Expected Semicolon, found RightBrace\n  --> m.proto:4:1\n  |\n4 | }\n  | ^"
+        );
+    }
+}
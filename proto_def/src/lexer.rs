@@ -1,10 +1,18 @@
 use logos::Logos;
 
+use crate::error::{Error, Span};
+
 #[derive(Logos, Debug, PartialEq)]
 pub enum Token {
     #[token("message")]
     Message,
 
+    #[token("enum")]
+    Enum,
+
+    #[token("oneof")]
+    Oneof,
+
     #[token("service")]
     Service,
 
@@ -20,12 +28,24 @@ pub enum Token {
     #[token("repeated")]
     Repeated,
 
+    #[token("optional")]
+    Optional,
+
+    #[token("required")]
+    Required,
+
+    #[token("group")]
+    Group,
+
     #[token(".")]
     Dot,
 
     #[token(",")]
     Comma,
 
+    #[token(":")]
+    Colon,
+
     #[token("package")]
     Package,
 
@@ -35,10 +55,15 @@ pub enum Token {
     #[token("syntax")]
     Syntax,
 
-    #[regex(r#""([^"\\]|\\.)*""#, |lex| {
-        let slice = lex.slice();
-        Some(slice[1..slice.len() - 1].to_string())
-    })]
+    #[token("reserved")]
+    Reserved,
+
+    #[token("extensions")]
+    Extensions,
+
+    /// A double- or single-quoted string, with its escapes decoded.
+    #[regex(r#""([^"\\\n]|\\.)*""#, |lex| unescape(lex.slice()))]
+    #[regex(r#"'([^'\\\n]|\\.)*'"#, |lex| unescape(lex.slice()))]
     Literal(String),
 
     #[token("int32")]
@@ -86,8 +111,21 @@ pub enum Token {
     #[token("float")]
     Float,
 
-    #[regex(r"[0-9]+", |lex| lex.slice().parse().ok())]
-    Number(u32),
+    /// A decimal, octal (`017`) or hexadecimal (`0x1F`) integer.
+    #[regex(r"[0-9]+|0[xX][0-9a-fA-F]+", |lex| integer(lex.slice()))]
+    Number(u64),
+
+    /// A decimal literal with a fraction or exponent, such as `1.5`, `.5` or
+    /// `1e-3`. `inf` and `nan` lex as identifiers.
+    #[regex(r"([0-9]+\.[0-9]*|\.[0-9]+)([eE][+-]?[0-9]+)?", |lex| lex.slice().parse().ok())]
+    #[regex(r"[0-9]+[eE][+-]?[0-9]+", |lex| lex.slice().parse().ok())]
+    FloatLiteral(f64),
+
+    #[token("-")]
+    Minus,
+
+    #[token("+")]
+    Plus,
 
     #[token(";")]
     Semicolon,
@@ -107,15 +145,123 @@ pub enum Token {
     #[token("=")]
     Equal,
 
+    #[token("<")]
+    LeftAngle,
+
+    #[token(">")]
+    RightAngle,
+
+    #[token("[")]
+    LeftBracket,
+
+    #[token("]")]
+    RightBracket,
+
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| Some(lex.slice().to_string()))]
     Identifier(String),
 
+    /// A `//` or `/* */` comment, as written. The parser attaches these to
+    /// the declarations around them.
+    #[regex(r"//[^\n]*", |lex| lex.slice().to_string())]
+    #[token("/*", block_comment)]
+    Comment(String),
+
     #[regex(r"[ \t\n\r\f]+", logos::skip)]
-    #[regex(r"//[^\n]*", logos::skip)]
-    #[regex(r"/\*([^*]|\*[^/])*\*/", logos::skip)]
     Error,
 }
 
+/// Extends a `/*` match through the closing `*/`. Done by hand since logos
+/// does not backtrack, which trips regexes over comments like `/* a **/`.
+fn block_comment(lex: &mut logos::Lexer<Token>) -> Option<String> {
+    let end = lex.remainder().find("*/")?;
+    lex.bump(end + 2);
+    Some(lex.slice().to_string())
+}
+
+fn integer(text: &str) -> Option<u64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        u64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Decodes the escapes of a quoted string: `\n` and the other C character
+/// escapes, `\x` hex and `\0`-`\377` octal bytes, and `\u`/`\U` code points.
+/// Byte escapes that don't form valid UTF-8 are replaced with U+FFFD.
+pub(crate) fn unescape(quoted: &str) -> Option<String> {
+    let mut chars = quoted[1..quoted.len() - 1].chars().peekable();
+    let mut bytes = Vec::new();
+    let push = |bytes: &mut Vec<u8>, c: char| {
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+    };
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            push(&mut bytes, c);
+            continue;
+        }
+        let escape = chars.next()?;
+        match escape {
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'f' => bytes.push(0x0c),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            'v' => bytes.push(0x0b),
+            '\\' | '\'' | '"' | '?' => push(&mut bytes, escape),
+            'x' | 'X' => {
+                let digits = take_digits(&mut chars, 16, 2);
+                bytes.push(u8::from_str_radix(&digits, 16).ok()?);
+            }
+            '0'..='7' => {
+                let digits = escape.to_string() + &take_digits(&mut chars, 8, 2);
+                bytes.push(u8::try_from(u32::from_str_radix(&digits, 8).ok()?).ok()?);
+            }
+            'u' | 'U' => {
+                let len = if escape == 'u' { 4 } else { 8 };
+                let digits = take_digits(&mut chars, 16, len);
+                if digits.len() != len {
+                    return None;
+                }
+                push(
+                    &mut bytes,
+                    char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?,
+                );
+            }
+            _ => return None,
+        }
+    }
+
+    Some(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Takes up to `max` digits in `radix` from the front of `chars`.
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: usize) -> String {
+    let mut digits = String::new();
+    while digits.len() < max {
+        match chars.next_if(|c| c.is_digit(radix)) {
+            Some(c) => digits.push(c),
+            None => break,
+        }
+    }
+    digits
+}
+
+/// A token and where it was found in the source.
+#[derive(Debug, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Default)]
 pub struct Lexer;
 
 impl Lexer {
@@ -123,20 +269,20 @@ impl Lexer {
         Lexer
     }
 
-    pub fn lex(&self, input: &str) -> Result<Vec<Token>, String> {
+    pub fn lex(&self, input: &str) -> Result<Vec<SpannedToken>, Error> {
+        let lines = LineIndex::new(input);
         let mut lexer = Token::lexer(input);
         let mut tokens = Vec::new();
 
         while let Some(result) = lexer.next() {
+            let span = lines.span(input, lexer.span());
             match result {
-                Ok(token) => tokens.push(token),
+                Ok(token) => tokens.push(SpannedToken { token, span }),
                 Err(_) => {
-                    let span = lexer.span();
-                    let slice = &input[span.clone()];
-                    return Err(format!(
-                        "Failed to tokenize proto file at {:?}: '{}'",
-                        span, slice
-                    ));
+                    return Err(Error::Lex {
+                        span,
+                        text: lexer.slice().to_string(),
+                    });
                 }
             }
         }
@@ -145,15 +291,78 @@ impl Lexer {
     }
 }
 
+/// Byte offsets of line starts, for turning byte ranges into line/column.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(input: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { starts }
+    }
+
+    fn span(&self, input: &str, range: std::ops::Range<usize>) -> Span {
+        let line = self.starts.partition_point(|&start| start <= range.start);
+        let line_start = self.starts[line - 1];
+        Span {
+            start: range.start,
+            end: range.end,
+            line,
+            column: input[line_start..range.start].chars().count() + 1,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_token_spans() {
+        let input = "message M {\n  /* ä */ string name = 1;\n}";
+        let tokens = Lexer::new().lex(input).unwrap();
+
+        assert_eq!(
+            tokens[0].span,
+            Span {
+                start: 0,
+                end: 7,
+                line: 1,
+                column: 1
+            }
+        );
+        let equal = tokens.iter().find(|t| t.token == Token::Equal).unwrap();
+        assert_eq!((equal.span.line, equal.span.column), (2, 23));
+        assert_eq!(&input[equal.span.start..equal.span.end], "=");
+
+        let error = Lexer::new().lex("message M {\n  @\n}").unwrap_err();
+        assert_eq!(
+            error,
+            Error::Lex {
+                span: Span {
+                    start: 14,
+                    end: 15,
+                    line: 2,
+                    column: 3
+                },
+                text: "@".into()
+            }
+        );
+    }
+
     #[test]
     fn test_tokenize_keywords() {
         let input = r#"package my.pkg; import "auth.proto"; option java_package = "com.example";"#;
         let lexer = Lexer::new();
-        let tokens = lexer.lex(input).unwrap();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
 
         let expected = vec![
             Token::Package,
@@ -178,7 +387,12 @@ mod tests {
     fn test_tokenize_message_block() {
         let input = r#"message User { string name = 1; int32 age = 2; }"#;
         let lexer = Lexer::new();
-        let tokens = lexer.lex(input).unwrap();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
 
         let expected = vec![
             Token::Message,
@@ -204,7 +418,12 @@ mod tests {
     fn test_tokenize_service_rpc() {
         let input = r#"service Auth { rpc Login (LoginRequest) returns (LoginResponse); }"#;
         let lexer = Lexer::new();
-        let tokens = lexer.lex(input).unwrap();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
 
         let expected = vec![
             Token::Service,
@@ -226,11 +445,72 @@ mod tests {
         assert_eq!(tokens, expected);
     }
 
+    #[test]
+    fn test_tokenize_enum_block() {
+        let input = r#"enum Role { ROLE_UNSPECIFIED = 0; ROLE_ADMIN = 1; }"#;
+        let lexer = Lexer::new();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+
+        let expected = vec![
+            Token::Enum,
+            Token::Identifier("Role".into()),
+            Token::LeftBrace,
+            Token::Identifier("ROLE_UNSPECIFIED".into()),
+            Token::Equal,
+            Token::Number(0),
+            Token::Semicolon,
+            Token::Identifier("ROLE_ADMIN".into()),
+            Token::Equal,
+            Token::Number(1),
+            Token::Semicolon,
+            Token::RightBrace,
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_tokenize_map_field() {
+        let input = r#"map<string, int64> labels = 4;"#;
+        let lexer = Lexer::new();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+
+        let expected = vec![
+            Token::Identifier("map".into()),
+            Token::LeftAngle,
+            Token::Str,
+            Token::Comma,
+            Token::Int64,
+            Token::RightAngle,
+            Token::Identifier("labels".into()),
+            Token::Equal,
+            Token::Number(4),
+            Token::Semicolon,
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+
     #[test]
     fn test_tokenize_with_repeated_fields() {
         let input = r#"message Post { repeated string tags = 1; }"#;
         let lexer = Lexer::new();
-        let tokens = lexer.lex(input).unwrap();
+        let tokens: Vec<Token> = lexer
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
 
         let expected = vec![
             Token::Message,
@@ -247,4 +527,97 @@ mod tests {
 
         assert_eq!(tokens, expected);
     }
+
+    fn tokens(input: &str) -> Vec<Token> {
+        Lexer::new()
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_numeric_literals() {
+        let input = "0 42 0x1F 0XfF 017 18446744073709551615 1.5 1. .5 1e3 2.5E-2 -1 +inf";
+
+        let expected = vec![
+            Token::Number(0),
+            Token::Number(42),
+            Token::Number(31),
+            Token::Number(255),
+            Token::Number(15),
+            Token::Number(u64::MAX),
+            Token::FloatLiteral(1.5),
+            Token::FloatLiteral(1.0),
+            Token::FloatLiteral(0.5),
+            Token::FloatLiteral(1000.0),
+            Token::FloatLiteral(0.025),
+            Token::Minus,
+            Token::Number(1),
+            Token::Plus,
+            Token::Identifier("inf".into()),
+        ];
+
+        assert_eq!(tokens(input), expected);
+    }
+
+    #[test]
+    fn test_tokenize_string_literals() {
+        let input = r#"'single' "it's" 'say "hi"' "a\tb\n" "\x41\101\0" "\u00e9\U0001F600" "\\\'\"\?" "\xc3\xa9""#;
+
+        let expected = vec![
+            Token::Literal("single".into()),
+            Token::Literal("it's".into()),
+            Token::Literal("say \"hi\"".into()),
+            Token::Literal("a\tb\n".into()),
+            Token::Literal("AA\0".into()),
+            Token::Literal("é😀".into()),
+            Token::Literal("\\'\"?".into()),
+            Token::Literal("é".into()),
+        ];
+
+        assert_eq!(tokens(input), expected);
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        let input = "/***/ M /* a **/ // b\n/** c * d */";
+        let tokens: Vec<Token> = Lexer::new()
+            .lex(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Comment("/***/".into()),
+                Token::Identifier("M".into()),
+                Token::Comment("/* a **/".into()),
+                Token::Comment("// b".into()),
+                Token::Comment("/** c * d */".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_literals() {
+        for input in [
+            r#""\q""#,
+            r#""\u12""#,
+            r#""\400""#,
+            "\"line\nbreak\"",
+            "'unterminated",
+            "09",
+            "0x10000000000000000",
+        ] {
+            assert!(
+                matches!(Lexer::new().lex(input), Err(Error::Lex { .. })),
+                "{:?} should not lex",
+                input
+            );
+        }
+    }
 }
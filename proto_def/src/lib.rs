@@ -1,3 +1,11 @@
+pub mod descriptor;
+pub mod error;
 pub mod lexer;
+pub mod model;
 pub mod parser;
-pub mod model;
\ No newline at end of file
+pub mod printer;
+pub mod resolver;
+pub mod validate;
+pub mod well_known;
+
+pub use error::Error;
@@ -1,51 +1,510 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::RangeInclusive};
 
-#[derive(Debug)]
+use crate::error::Span;
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Proto {
     pub package: Option<String>,
     pub syntax: Option<String>,
+    /// The edition of an Editions file, such as `2023`. `syntax` is unset
+    /// then.
+    pub edition: Option<String>,
     pub options: HashMap<String, String>,
     pub imports: Vec<String>,
+    /// The imports declared `import public`, re-exported to importers.
+    pub public_imports: Vec<String>,
+    /// The imports declared `import weak`.
+    pub weak_imports: Vec<String>,
     pub services: Vec<Service>,
     pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+    /// Top-level `extend` blocks; those nested in a message are on the message.
+    pub extends: Vec<Extend>,
+    /// Comments at the top of the file, before the first statement.
+    pub comments: Comments,
+}
+
+/// A named type declared in a proto file.
+#[derive(Debug, Clone, Copy)]
+pub enum Definition<'a> {
+    Message(&'a Message),
+    Enum(&'a Enum),
+}
+
+impl<'a> Definition<'a> {
+    pub fn full_name(&self) -> &'a str {
+        match self {
+            Definition::Message(m) => &m.full_name,
+            Definition::Enum(e) => &e.full_name,
+        }
+    }
+}
+
+impl Proto {
+    /// Files without a `syntax` or `edition` statement are proto2, as in
+    /// protoc.
+    pub fn is_proto2(&self) -> bool {
+        self.edition.is_none() && self.syntax.as_deref().is_none_or(|s| s == "proto2")
+    }
+
+    /// The `field_presence` feature in effect for `field`, declared in the
+    /// message named `message`. In proto2 and proto3 files it follows from the
+    /// syntax and the field's label. In Editions files the closest
+    /// `features.field_presence` option wins, looking at the field, its
+    /// enclosing messages and then the file, and presence is explicit by
+    /// default.
+    ///
+    /// This doesn't look at the field's type: message fields track presence
+    /// whatever the feature says, and repeated fields never do.
+    pub fn field_presence(&self, message: &str, field: &Field) -> FieldPresence {
+        if self.edition.is_none() {
+            return match field.label {
+                Some(Label::Required) => FieldPresence::LegacyRequired,
+                Some(Label::Optional) => FieldPresence::Explicit,
+                None if self.is_proto2() => FieldPresence::Explicit,
+                None => FieldPresence::Implicit,
+            };
+        }
+
+        let scopes = std::iter::once(&self.options)
+            .chain(self.enclosing(message).into_iter().map(|m| &m.options))
+            .chain(std::iter::once(&field.options));
+        let feature = scopes.filter_map(|o| o.get(FIELD_PRESENCE)).next_back();
+        match feature.map(String::as_str) {
+            Some("IMPLICIT") => FieldPresence::Implicit,
+            Some("LEGACY_REQUIRED") => FieldPresence::LegacyRequired,
+            _ => FieldPresence::Explicit,
+        }
+    }
+
+    /// The message named `full_name` and the messages it is nested in,
+    /// outermost first.
+    fn enclosing(&self, full_name: &str) -> Vec<&Message> {
+        let full_name = full_name.strip_prefix('.').unwrap_or(full_name);
+        let mut path = Vec::new();
+        let mut messages = &self.messages;
+        while let Some(m) = messages.iter().find(|m| {
+            full_name
+                .strip_prefix(m.full_name.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        }) {
+            path.push(m);
+            messages = &m.messages;
+        }
+        path
+    }
+
+    /// Every `extend` block in the file, including those nested in messages.
+    pub fn all_extends(&self) -> Vec<&Extend> {
+        fn collect<'a>(messages: &'a [Message], extends: &mut Vec<&'a Extend>) {
+            for message in messages {
+                extends.extend(&message.extends);
+                collect(&message.messages, extends);
+            }
+        }
+
+        let mut extends: Vec<&Extend> = self.extends.iter().collect();
+        collect(&self.messages, &mut extends);
+        extends
+    }
+
+    /// Looks up a message or enum (top-level or nested) by its fully-qualified name.
+    pub fn find(&self, full_name: &str) -> Option<Definition<'_>> {
+        let full_name = full_name.strip_prefix('.').unwrap_or(full_name);
+        if let Some(e) = self.enums.iter().find(|e| e.full_name == full_name) {
+            return Some(Definition::Enum(e));
+        }
+        find_in(&self.messages, full_name)
+    }
+
+    /// Looks up a message (top-level or nested) by its fully-qualified name.
+    pub fn find_message(&self, full_name: &str) -> Option<&Message> {
+        match self.find(full_name) {
+            Some(Definition::Message(m)) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Resolves a type reference the way protoc does: starting from `scope`,
+    /// the innermost enclosing scope is searched first, then each parent.
+    /// See [`resolve_in_scope`] for qualified names.
+    pub fn resolve(&self, scope: &str, name: &str) -> Option<Definition<'_>> {
+        resolve_in_scope(
+            scope,
+            name,
+            |candidate| self.find(candidate),
+            |candidate| self.in_package(candidate),
+        )
+    }
+
+    /// Whether `name` is this file's package or one of its parent packages.
+    pub fn in_package(&self, name: &str) -> bool {
+        self.package.as_deref().is_some_and(|package| {
+            package == name
+                || package
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Same as [`Proto::resolve`], restricted to messages.
+    pub fn resolve_message(&self, scope: &str, name: &str) -> Option<&Message> {
+        match self.resolve(scope, name) {
+            Some(Definition::Message(m)) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns the fully-qualified name with the package prefix stripped.
+    pub fn relative_name<'a>(&self, full_name: &'a str) -> &'a str {
+        match &self.package {
+            Some(package) => full_name
+                .strip_prefix(package.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+                .unwrap_or(full_name),
+            None => full_name,
+        }
+    }
 }
 
-impl Default for Proto {
-    fn default() -> Self {
-        Proto {
-            package: None,
-            syntax: Some("proto3".to_string()),
-            options: HashMap::new(),
-            imports: Vec::new(),
-            services: Vec::new(),
-            messages: Vec::new(),
+/// Resolves `name` the way protoc does. A leading dot marks a fully-qualified
+/// name. Otherwise the first component of `name` is searched from the
+/// innermost scope outward, and the rest of the name is looked up inside the
+/// first scope declaring it, so `b.C` from `a` never falls back to `.b.C` once
+/// `a.b` exists.
+pub(crate) fn resolve_in_scope<'a>(
+    scope: &str,
+    name: &str,
+    find: impl Fn(&str) -> Option<Definition<'a>>,
+    is_package: impl Fn(&str) -> bool,
+) -> Option<Definition<'a>> {
+    if let Some(full_name) = name.strip_prefix('.') {
+        return find(full_name);
+    }
+
+    let first = name.split('.').next().unwrap_or(name);
+    let mut scope = scope.to_string();
+    loop {
+        let prefix = if scope.is_empty() {
+            String::new()
+        } else {
+            format!("{}.", scope)
+        };
+        let candidate = format!("{}{}", prefix, first);
+        let declared = if first == name {
+            find(&candidate).is_some()
+        } else {
+            // Only messages and packages can contain the remaining components.
+            matches!(find(&candidate), Some(Definition::Message(_))) || is_package(&candidate)
+        };
+        if declared {
+            return find(&format!("{}{}", prefix, name));
+        }
+        if scope.is_empty() {
+            return None;
+        }
+        scope = match scope.rfind('.') {
+            Some(idx) => scope[..idx].to_string(),
+            None => String::new(),
+        };
+    }
+}
+
+fn find_in<'a>(messages: &'a [Message], full_name: &str) -> Option<Definition<'a>> {
+    for message in messages {
+        if message.full_name == full_name {
+            return Some(Definition::Message(message));
         }
+        if full_name.starts_with(&format!("{}.", message.full_name)) {
+            if let Some(e) = message.enums.iter().find(|e| e.full_name == full_name) {
+                return Some(Definition::Enum(e));
+            }
+            if let Some(found) = find_in(&message.messages, full_name) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+/// Comments around a declaration, as written with their `//` or `/* */`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comments {
+    /// Comments on the lines before the declaration. An empty string stands
+    /// for a blank line, so detached comments stay apart from the doc comment.
+    pub leading: Vec<String>,
+    /// A comment after the declaration, on the line it ends.
+    pub trailing: Option<String>,
+}
+
+impl Comments {
+    /// The text of the comments directly above the declaration, without
+    /// comment markers, as protoc reports them for documentation.
+    pub fn doc(&self) -> Option<String> {
+        let attached = match self.leading.iter().rposition(String::is_empty) {
+            Some(blank) => &self.leading[blank + 1..],
+            None => &self.leading[..],
+        };
+        let lines: Vec<&str> = attached.iter().flat_map(|c| comment_lines(c)).collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
     }
 }
 
+/// Strips `//`, `/*`, `*/` and the leading `*` of block comment lines.
+fn comment_lines(comment: &str) -> Vec<&str> {
+    if let Some(line) = comment.strip_prefix("//") {
+        return vec![line.strip_prefix(' ').unwrap_or(line).trim_end()];
+    }
+    let body = comment
+        .strip_prefix("/*")
+        .and_then(|c| c.strip_suffix("*/"))
+        .unwrap_or(comment);
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+    // Drop the empty lines left by `/**` and ` */` on their own lines.
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].to_vec()
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Service {
     pub name: String,
     pub methods: HashMap<String, RpcMethod>,
+    pub comments: Comments,
+    /// From `service` through the closing brace.
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RpcMethod {
     pub name: String,
     pub request: String,
     pub response: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub options: HashMap<String, String>,
+    pub http: Option<HttpRule>,
+    pub comments: Comments,
+    /// The declaration up to the response type.
+    pub span: Span,
+}
+
+impl RpcMethod {
+    /// The custom options set on this method: the extension name written in
+    /// parentheses, the path to a field of an aggregate value (empty
+    /// otherwise) and the value. `option (acme.auth) = { required: true };`
+    /// is kept in `options` as `(acme.auth).required` and gives
+    /// `("acme.auth", "required", "true")`.
+    pub fn custom_options(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.options.iter().filter_map(|(name, value)| {
+            let (extension, path) = name.strip_prefix('(')?.split_once(')')?;
+            Some((extension, path.trim_start_matches('.'), value.as_str()))
+        })
+    }
+}
+
+/// A `google.api.http` annotation on an RPC.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HttpRule {
+    pub method: String,
+    pub path: String,
+    pub body: Option<String>,
+    pub additional_bindings: Vec<HttpRule>,
+}
+
+/// The value of an option statement: a single constant, or a text-format
+/// message literal such as `{ get: "/v1/books" body: "*" }`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum OptionValue {
+    Scalar(String),
+    Aggregate(Vec<(String, OptionValue)>),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Message {
+    pub name: String,
+    pub full_name: String,
+    pub fields: Vec<Field>,
+    pub oneofs: Vec<Oneof>,
+    pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+    pub options: HashMap<String, String>,
+    /// Field numbers given by `reserved`, as inclusive ranges.
+    pub reserved_numbers: Vec<RangeInclusive<u32>>,
+    pub reserved_names: Vec<String>,
+    /// Field numbers given by `extensions`, as inclusive ranges.
+    pub extensions: Vec<RangeInclusive<u32>>,
+    pub extends: Vec<Extend>,
+    pub comments: Comments,
+    /// From `message` (or a group's label) through the closing brace.
+    pub span: Span,
+}
+
+/// An `extend` block, adding fields to another message. Most declare custom
+/// options, extending one of the `google.protobuf.*Options` messages.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Extend {
+    /// The extended message, as written.
+    pub extendee: String,
+    /// The package or message the block is declared in, which qualifies the
+    /// names of its fields.
+    pub scope: String,
+    pub fields: Vec<Field>,
+    pub comments: Comments,
+    /// From `extend` through the closing brace.
+    pub span: Span,
+}
+
+impl Extend {
+    /// The fully-qualified name of `field`, one of this block's fields. This
+    /// is the name custom options refer to, as in `(acme.auth.required)`.
+    pub fn full_name(&self, field: &Field) -> String {
+        if self.scope.is_empty() {
+            field.name.clone()
+        } else {
+            format!("{}.{}", self.scope, field.name)
+        }
+    }
+}
+
+/// A `oneof` group. Its members are kept out of `Message::fields`, mirroring
+/// how prost and protoc-gen-java expose them.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Oneof {
     pub name: String,
     pub fields: Vec<Field>,
+    pub comments: Comments,
+    /// From `oneof` through the closing brace.
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Field {
     pub name: String,
     pub field_type: String,
     pub number: u32,
     pub repeated: bool,
+    /// Explicit `optional` or `required` label; `repeated` is tracked above.
+    pub label: Option<Label>,
+    /// Key type of a `map<K, V>` field; `field_type` then holds `V`.
+    pub key_type: Option<String>,
+    /// Options in brackets after the field number, e.g. `[deprecated = true]`.
+    pub options: HashMap<String, String>,
+    /// Declared as a proto2 `group`; `field_type` is then the nested message
+    /// holding the group's body.
+    pub group: bool,
+    pub comments: Comments,
+    /// The whole declaration, from label to semicolon.
+    pub span: Span,
+}
+
+impl Field {
+    /// The name used for this field in JSON, when `json_name` overrides it.
+    pub fn json_name(&self) -> Option<&str> {
+        self.options.get("json_name").map(String::as_str)
+    }
+
+    pub fn deprecated(&self) -> bool {
+        self.options.get("deprecated").is_some_and(|v| v == "true")
+    }
+
+    /// The proto2 `[default = ...]` value, as written.
+    pub fn default_value(&self) -> Option<&str> {
+        self.options.get("default").map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Label {
+    Optional,
+    Required,
+}
+
+/// The option setting the `field_presence` feature in Editions files.
+pub const FIELD_PRESENCE: &str = "features.field_presence";
+
+/// Whether a singular field tracks presence, the `field_presence` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldPresence {
+    /// Unset and default values are told apart, as with `optional`.
+    Explicit,
+    /// A default value means unset, as for plain proto3 fields.
+    Implicit,
+    /// The field must be set, as with proto2 `required`.
+    LegacyRequired,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Enum {
+    pub name: String,
+    pub full_name: String,
+    pub values: Vec<EnumValue>,
+    pub allow_alias: bool,
+    /// Options other than `allow_alias`.
+    pub options: HashMap<String, String>,
+    /// Value numbers given by `reserved`, as inclusive ranges.
+    pub reserved_numbers: Vec<RangeInclusive<i32>>,
+    pub reserved_names: Vec<String>,
+    pub comments: Comments,
+    /// From `enum` through the closing brace.
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EnumValue {
+    pub name: String,
+    pub number: i32,
+    pub options: HashMap<String, String>,
+    pub comments: Comments,
+}
+
+/// The largest field number protoc accepts, `max` in ranges.
+pub const MAX_FIELD_NUMBER: u32 = 536_870_911;
+
+/// Whether `type_name` is a scalar value type rather than a message or enum.
+pub fn is_scalar(type_name: &str) -> bool {
+    matches!(
+        type_name,
+        "double"
+            | "float"
+            | "int32"
+            | "int64"
+            | "uint32"
+            | "uint64"
+            | "sint32"
+            | "sint64"
+            | "fixed32"
+            | "fixed64"
+            | "sfixed32"
+            | "sfixed64"
+            | "bool"
+            | "string"
+            | "bytes"
+    )
 }
@@ -1,133 +1,583 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::RangeInclusive};
 
 use crate::{
-    lexer::Token,
-    model::{Field, Message, Proto, RpcMethod, Service},
+    error::{Error, Span},
+    lexer::{SpannedToken, Token},
+    model::{
+        Comments, Enum, EnumValue, Extend, Field, HttpRule, Label, MAX_FIELD_NUMBER, Message,
+        Oneof, OptionValue, Proto, RpcMethod, Service,
+    },
+    printer,
 };
 
 pub struct Parser<'a> {
-    tokens: &'a [Token],
+    tokens: Vec<&'a SpannedToken>,
+    /// Comment tokens, kept out of `tokens` and attached to declarations.
+    comments: Vec<&'a SpannedToken>,
     pos: usize,
+    /// Set once an `edition` statement is read, which rules out labels and
+    /// groups.
+    editions: bool,
+    /// Set by [`Parser::lossless`].
+    lossless: bool,
+    /// Errors recovered from so far, see [`Parser::parse_partial`].
+    errors: Vec<Error>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a [Token]) -> Self {
-        Parser { tokens, pos: 0 }
+    pub fn new(tokens: &'a [SpannedToken]) -> Self {
+        let (comments, tokens) = tokens
+            .iter()
+            .partition(|t| matches!(t.token, Token::Comment(_)));
+        Parser {
+            tokens,
+            comments,
+            pos: 0,
+            editions: false,
+            lossless: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Also reports, as errors, what the model can't hold as written and
+    /// [`printer::print`] would therefore change: aggregate option values
+    /// outside an RPC, options on extension ranges, and constants whose
+    /// quoting the printer can't tell from their value.
+    pub fn lossless(mut self) -> Self {
+        self.lossless = true;
+        self
     }
 
-    fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.pos)
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos).copied().map(|t| &t.token)
     }
 
-    fn next(&mut self) -> Option<&Token> {
-        let token = self.tokens.get(self.pos);
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos).copied().map(|t| &t.token);
         if token.is_some() {
             self.pos += 1;
         }
         token
     }
 
-    fn expect(&mut self, expected: &Token) -> Result<(), String> {
+    fn expect(&mut self, expected: &Token) -> Result<(), Error> {
         match self.next() {
             Some(t) if t == expected => Ok(()),
-            _ => Err(format!(
-                "Expected {:?}, found {:?} at position {}",
-                expected,
-                self.peek(),
-                self.pos
-            )),
+            Some(t) => Err(self.error(format!("Expected {:?}, found {:?}", expected, t))),
+            None => Err(self.error_here(format!("Expected {:?}, found end of input", expected))),
+        }
+    }
+
+    /// An error pointing at the token just consumed.
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos.saturating_sub(1), message)
+    }
+
+    /// An error pointing at the upcoming token.
+    fn error_here(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, index: usize, message: impl Into<String>) -> Error {
+        let span = match (self.tokens.get(index), self.tokens.last()) {
+            (Some(token), _) => token.span,
+            // Past the last token: point just after it.
+            (None, Some(last)) => Span {
+                start: last.span.end,
+                end: last.span.end,
+                line: last.span.line,
+                column: last.span.column + (last.span.end - last.span.start),
+            },
+            (None, None) => Span {
+                line: 1,
+                column: 1,
+                ..Span::default()
+            },
+        };
+        Error::Parse {
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// Records that the tokens from `start` to the last consumed one can't be
+    /// printed back as written, when parsing [`Parser::lossless`]ly.
+    fn lossy(&mut self, start: usize, message: impl Into<String>) {
+        if self.lossless {
+            self.errors.push(Error::Parse {
+                span: self.span_from(start),
+                message: format!("Formatting would {}", message.into()),
+            });
+        }
+    }
+
+    /// Parses the statement at the current token with `parse`. On error, the
+    /// error is recorded and the rest of the statement skipped, so parsing can
+    /// resume with the next one.
+    fn statement<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Option<T> {
+        let start = self.pos;
+        match parse(self) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize(start);
+                None
+            }
+        }
+    }
+
+    /// Skips past the statement starting at token `start` that failed at the
+    /// current token: through its `;`, or the `}` closing a block it opened,
+    /// or up to the `}` closing the enclosing block.
+    fn synchronize(&mut self, start: usize) {
+        let failed = self.pos.saturating_sub(1).max(start);
+        let mut depth = 0usize;
+        let mut index = start;
+        while let Some(token) = self.tokens.get(index).map(|t| &t.token) {
+            match token {
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace if depth == 0 => {
+                    // A stray `}` is skipped; any other closes the enclosing
+                    // block, which must see it.
+                    self.pos = if index == start { index + 1 } else { index };
+                    return;
+                }
+                Token::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 && index >= failed {
+                        self.pos = index + 1;
+                        if let Some(Token::Semicolon) = self.peek() {
+                            self.pos += 1;
+                        }
+                        return;
+                    }
+                }
+                Token::Semicolon if depth == 0 && index >= failed => {
+                    self.pos = index + 1;
+                    return;
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+        self.pos = index;
+    }
+
+    /// The span from the token at `start` through the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        let first = self.tokens.get(start).map(|t| t.span).unwrap_or_default();
+        let end = if self.pos > start {
+            self.tokens[self.pos - 1].span.end
+        } else {
+            first.end
+        };
+        Span { end, ..first }
+    }
+
+    /// The comments of the declaration starting at token `start` and ending
+    /// with the last consumed token.
+    fn comments(&self, start: usize) -> Comments {
+        Comments {
+            leading: self.leading_comments(start),
+            trailing: self.trailing_comment(),
+        }
+    }
+
+    /// Comments between the previous statement and the token at `index`,
+    /// with blank lines between them kept as empty strings.
+    fn leading_comments(&self, index: usize) -> Vec<String> {
+        let Some(token) = self.tokens.get(index) else {
+            return Vec::new();
+        };
+        let previous = index.checked_sub(1).map(|i| self.tokens[i]);
+        let mut line = previous.map_or(0, |p| p.span.line);
+
+        let mut leading = Vec::new();
+        for comment in self.comments_between(previous.map_or(0, |p| p.span.end), token.span.start) {
+            // A comment on the line a statement ends belongs to that statement.
+            if previous
+                .is_some_and(|p| ends_statement(&p.token) && p.span.line == comment.span.line)
+            {
+                continue;
+            }
+            if line > 0 && comment.span.line > line + 1 {
+                leading.push(String::new());
+            }
+            if let Token::Comment(text) = &comment.token {
+                leading.push(text.clone());
+                line = comment.span.line + text.matches('\n').count();
+            }
+        }
+        if line > 0 && token.span.line > line + 1 {
+            leading.push(String::new());
         }
+        leading
+    }
+
+    /// A comment on the same line as the last consumed token, before the
+    /// next one.
+    fn trailing_comment(&self) -> Option<String> {
+        let last = self.tokens.get(self.pos.checked_sub(1)?)?;
+        let next = self
+            .tokens
+            .get(self.pos)
+            .map_or(usize::MAX, |t| t.span.start);
+        match self.comments_between(last.span.end, next).first() {
+            Some(SpannedToken {
+                token: Token::Comment(text),
+                span,
+            }) if span.line == last.span.line => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    fn comments_between(&self, start: usize, end: usize) -> &[&'a SpannedToken] {
+        let from = self.comments.partition_point(|c| c.span.start < start);
+        let to = self.comments.partition_point(|c| c.span.start < end);
+        &self.comments[from..to.max(from)]
     }
 
-    fn parse_dotted_identifier(&mut self) -> Result<String, String> {
+    fn parse_dotted_identifier(&mut self) -> Result<String, Error> {
         let mut parts = Vec::new();
 
         match self.next() {
             Some(Token::Identifier(name)) => parts.push(name.clone()),
-            _ => return Err("Expected identifier".into()),
+            _ => return Err(self.error("Expected identifier")),
         }
 
         while let Some(Token::Dot) = self.peek() {
             self.next();
-            match self.next() {
-                Some(Token::Identifier(name)) => parts.push(name.clone()),
-                _ => return Err("Expected identifier after '.'".into()),
+            match self.next().and_then(name_text) {
+                Some(name) => parts.push(name.to_string()),
+                None => return Err(self.error("Expected identifier after '.'")),
             }
         }
 
         Ok(parts.join("."))
     }
 
-    pub fn parse(&mut self) -> Result<Proto, String> {
+    /// Parses the whole file, failing with every syntax error found: the
+    /// only one, or [`Error::Multiple`].
+    pub fn parse(&mut self) -> Result<Proto, Error> {
+        let (content, mut errors) = self.parse_partial();
+        match errors.len() {
+            0 => Ok(content),
+            1 => Err(errors.remove(0)),
+            _ => Err(Error::Multiple(errors)),
+        }
+    }
+
+    /// Parses the whole file, recovering from syntax errors by skipping the
+    /// statement they occur in. Returns what could be parsed along with the
+    /// errors, in source order.
+    pub fn parse_partial(&mut self) -> (Proto, Vec<Error>) {
         let mut content = Proto::default();
+        if !matches!(
+            self.peek(),
+            Some(Token::Message | Token::Enum | Token::Service)
+        ) {
+            content.comments.leading = self.leading_comments(0);
+        }
 
         while let Some(token) = self.peek() {
-            match token {
-                Token::Package => {
-                    self.next();
-                    content.package = Some(self.parse_dotted_identifier()?);
-                    self.expect(&Token::Semicolon)?;
+            self.statement(|p| p.parse_file_statement(token, &mut content));
+        }
+
+        (content, std::mem::take(&mut self.errors))
+    }
+
+    fn parse_file_statement(&mut self, token: &Token, content: &mut Proto) -> Result<(), Error> {
+        match token {
+            Token::Package => {
+                self.next();
+                content.package = Some(self.parse_dotted_identifier()?);
+                self.expect(&Token::Semicolon)?;
+            }
+            Token::Syntax => {
+                self.next();
+                self.expect(&Token::Equal)?;
+                if let Some(Token::Literal(value)) = self.next() {
+                    content.syntax = Some(value.clone());
+                } else {
+                    return Err(self.error("Expected string literal after 'syntax ='"));
                 }
-                Token::Syntax => {
-                    self.next();
-                    self.expect(&Token::Equal)?;
-                    if let Some(Token::Literal(value)) = self.next() {
-                        content.syntax = Some(value.clone());
-                    } else {
-                        return Err("Expected string literal after 'syntax ='".into());
+                self.expect(&Token::Semicolon)?;
+            }
+            Token::Identifier(word) if word == "edition" => {
+                self.next();
+                self.expect(&Token::Equal)?;
+                if let Some(Token::Literal(value)) = self.next() {
+                    content.edition = Some(value.clone());
+                    self.editions = true;
+                } else {
+                    return Err(self.error("Expected string literal after 'edition ='"));
+                }
+                self.expect(&Token::Semicolon)?;
+            }
+            Token::Import => {
+                self.next();
+                let modifier = match self.peek() {
+                    Some(Token::Identifier(modifier))
+                        if modifier == "public" || modifier == "weak" =>
+                    {
+                        self.next();
+                        Some(modifier)
+                    }
+                    _ => None,
+                };
+                if let Some(Token::Literal(name)) = self.next() {
+                    content.imports.push(name.clone());
+                    match modifier.map(String::as_str) {
+                        Some("public") => content.public_imports.push(name.clone()),
+                        Some(_) => content.weak_imports.push(name.clone()),
+                        None => {}
                     }
-                    self.expect(&Token::Semicolon)?;
                 }
-                Token::Import => {
-                    self.next();
-                    if let Some(Token::Literal(name)) = self.next() {
-                        content.imports.push(name.clone());
+                self.expect(&Token::Semicolon)?;
+            }
+            Token::Option => {
+                if let Some((name, value)) = self.parse_scalar_option()? {
+                    content.options.insert(name, value);
+                }
+            }
+            Token::Message => {
+                let scope = content.package.clone().unwrap_or_default();
+                content.messages.push(self.parse_message(&scope)?);
+            }
+            Token::Enum => {
+                let scope = content.package.clone().unwrap_or_default();
+                content.enums.push(self.parse_enum(&scope)?);
+            }
+            Token::Service => {
+                content.services.push(self.parse_service()?);
+            }
+            Token::Identifier(word) if word == "extend" => {
+                let scope = content.package.clone().unwrap_or_default();
+                let extend = self.parse_extend(&scope, &mut content.messages)?;
+                content.extends.push(extend);
+            }
+            _ => return Err(self.error_here(format!("Unexpected token: {:?}", token))),
+        }
+        Ok(())
+    }
+
+    fn parse_message(&mut self, scope: &str) -> Result<Message, Error> {
+        let start = self.pos;
+        self.expect(&Token::Message)?;
+        let name = match self.next() {
+            Some(Token::Identifier(n)) => n.clone(),
+            _ => return Err(self.error("Expected identifier after 'message'")),
+        };
+        let mut message = self.parse_message_body(start, name, scope)?;
+        message.comments = self.comments(start);
+        Ok(message)
+    }
+
+    /// Parses `{ ... }` declaring message `name`, for a `message` or `group`
+    /// starting at token `start`.
+    fn parse_message_body(
+        &mut self,
+        start: usize,
+        name: String,
+        scope: &str,
+    ) -> Result<Message, Error> {
+        self.expect(&Token::LeftBrace)?;
+
+        let full_name = if scope.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", scope, name)
+        };
+
+        let mut fields = Vec::new();
+        let mut oneofs = Vec::new();
+        let mut messages = Vec::new();
+        let mut enums = Vec::new();
+        let mut options = HashMap::new();
+        let mut reserved_numbers = Vec::new();
+        let mut reserved_names = Vec::new();
+        let mut extensions = Vec::new();
+        let mut extends = Vec::new();
+        while let Some(token) = self.peek() {
+            if let Token::RightBrace = token {
+                self.next();
+                break;
+            }
+            self.statement(|p| {
+                match token {
+                    Token::Option => {
+                        if let Some((name, value)) = p.parse_scalar_option()? {
+                            options.insert(name, value);
+                        }
+                    }
+                    Token::Reserved => {
+                        p.next();
+                        if let Some(Token::Literal(_) | Token::Identifier(_)) = p.peek() {
+                            reserved_names.extend(p.parse_reserved_names()?);
+                        } else {
+                            reserved_numbers.extend(p.parse_ranges()?);
+                        }
+                        p.expect(&Token::Semicolon)?;
+                    }
+                    Token::Extensions => {
+                        p.next();
+                        extensions.extend(p.parse_ranges()?);
+                        if let Some(Token::LeftBracket) = p.peek() {
+                            let start = p.pos;
+                            p.parse_bracket_options()?;
+                            p.lossy(start, "drop the options of an extension range");
+                        }
+                        p.expect(&Token::Semicolon)?;
+                    }
+                    Token::Message => {
+                        messages.push(p.parse_message(&full_name)?);
+                    }
+                    Token::Enum => {
+                        enums.push(p.parse_enum(&full_name)?);
+                    }
+                    Token::Oneof => {
+                        oneofs.push(p.parse_oneof(&full_name, &mut messages)?);
+                    }
+                    Token::Identifier(word) if word == "extend" => {
+                        extends.push(p.parse_extend(&full_name, &mut messages)?);
+                    }
+                    Token::Identifier(_)
+                    | Token::Dot
+                    | Token::Repeated
+                    | Token::Optional
+                    | Token::Required
+                    | Token::Group
+                    | Token::Int32
+                    | Token::Str
+                    | Token::Bool
+                    | Token::Int64
+                    | Token::Uint32
+                    | Token::Uint64
+                    | Token::Bytes
+                    | Token::Double
+                    | Token::Float
+                    | Token::Sfixed32
+                    | Token::Sfixed64
+                    | Token::Fixed32
+                    | Token::Fixed64
+                    | Token::Sint32
+                    | Token::Sint64 => {
+                        fields.push(p.parse_field(&full_name, &mut messages)?);
+                    }
+                    _ => {
+                        return Err(
+                            p.error_here(format!("Unexpected token in message: {:?}", token))
+                        );
                     }
-                    self.expect(&Token::Semicolon)?;
                 }
-                Token::Option => {
+                Ok(())
+            });
+        }
+
+        Ok(Message {
+            name,
+            full_name,
+            fields,
+            oneofs,
+            messages,
+            enums,
+            options,
+            reserved_numbers,
+            reserved_names,
+            extensions,
+            extends,
+            comments: Comments::default(),
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `2, 15, 9 to 11, 40 to max` as used by `reserved` and
+    /// `extensions`.
+    fn parse_ranges(&mut self) -> Result<Vec<RangeInclusive<u32>>, Error> {
+        let mut ranges = Vec::new();
+        loop {
+            let start = self.parse_field_number()?;
+            let end = match self.peek() {
+                Some(Token::Identifier(to)) if to == "to" => {
                     self.next();
-                    if let Some(Token::Identifier(name)) = self.next() {
-                        let name = name.clone();
-                        self.expect(&Token::Equal)?;
-                        if let Some(token) = self.next() {
-                            let value = match token {
-                                Token::Literal(s) => s.clone(),
-                                Token::Bool => "true".to_string(),
-                                Token::Identifier(s) => s.clone(),
-                                Token::Number(n) => n.to_string(),
-                                _ => {
-                                    return Err(format!(
-                                        "Unexpected token for option value: {:?}",
-                                        token
-                                    ));
-                                }
-                            };
-                            content.options.insert(name, value);
-                        } else {
-                            return Err("Expected option value after '='".into());
+                    match self.peek() {
+                        Some(Token::Identifier(max)) if max == "max" => {
+                            self.next();
+                            MAX_FIELD_NUMBER
                         }
-                        self.expect(&Token::Semicolon)?;
+                        _ => self.parse_field_number()?,
                     }
                 }
-                Token::Message => {
-                    content.messages.push(self.parse_message()?);
+                _ => start,
+            };
+            if end < start {
+                return Err(self.error(format!("Invalid range {} to {}", start, end)));
+            }
+            ranges.push(start..=end);
+
+            match self.peek() {
+                Some(Token::Comma) => {
+                    self.next();
+                }
+                _ => return Ok(ranges),
+            }
+        }
+    }
+
+    /// Parses reserved field names, quoted or (in editions) bare.
+    fn parse_reserved_names(&mut self) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Literal(name) | Token::Identifier(name)) => names.push(name.clone()),
+                other => {
+                    return Err(self.error(format!("Expected field name, got {:?}", other)));
+                }
+            }
+            match self.peek() {
+                Some(Token::Comma) => {
+                    self.next();
                 }
-                Token::Service => {
-                    content.services.push(self.parse_service()?);
+                _ => return Ok(names),
+            }
+        }
+    }
+
+    /// Parses an `extend` block declared in `scope`. Groups among its fields
+    /// declare their messages in `scope` too, so they go to `groups`.
+    fn parse_extend(&mut self, scope: &str, groups: &mut Vec<Message>) -> Result<Extend, Error> {
+        let start = self.pos;
+        self.next();
+        let extendee = self.parse_type_reference()?;
+        self.expect(&Token::LeftBrace)?;
+
+        let mut fields = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::RightBrace) => {
+                    self.next();
+                    break;
                 }
-                _ => return Err(format!("Unexpected token: {:?}", token)),
+                Some(_) => fields.push(self.parse_field(scope, groups)?),
+                None => return Err(self.error_here("Expected '}' to close 'extend'")),
             }
         }
 
-        Ok(content)
+        Ok(Extend {
+            extendee,
+            scope: scope.to_string(),
+            fields,
+            comments: self.comments(start),
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_message(&mut self) -> Result<Message, String> {
-        self.expect(&Token::Message)?;
+    fn parse_oneof(&mut self, scope: &str, groups: &mut Vec<Message>) -> Result<Oneof, Error> {
+        let start = self.pos;
+        self.expect(&Token::Oneof)?;
         let name = match self.next() {
             Some(Token::Identifier(n)) => n.clone(),
-            _ => return Err("Expected identifier after 'message'".into()),
+            _ => return Err(self.error("Expected identifier after 'oneof'")),
         };
         self.expect(&Token::LeftBrace)?;
 
@@ -138,78 +588,189 @@ impl<'a> Parser<'a> {
                     self.next();
                     break;
                 }
-                Token::Identifier(_)
-                | Token::Repeated
-                | Token::Int32
-                | Token::Str
-                | Token::Bool
-                | Token::Int64
-                | Token::Uint32
-                | Token::Uint64
-                | Token::Bytes
-                | Token::Double
-                | Token::Float
-                | Token::Sfixed32
-                | Token::Sfixed64
-                | Token::Fixed32
-                | Token::Fixed64
-                | Token::Sint32
-                | Token::Sint64 => {
-                    fields.push(self.parse_field()?);
+                Token::Repeated | Token::Optional | Token::Required => {
+                    return Err(
+                        self.error_here(format!("Fields in oneof '{}' cannot have labels", name))
+                    );
+                }
+                _ => {
+                    let field = self.parse_field(scope, groups)?;
+                    if field.key_type.is_some() {
+                        return Err(
+                            self.error(format!("Fields in oneof '{}' cannot be maps", name))
+                        );
+                    }
+                    fields.push(field);
                 }
-                _ => return Err(format!("Unexpected token in message: {:?}", token)),
             }
         }
 
-        Ok(Message { name, fields })
+        Ok(Oneof {
+            name,
+            fields,
+            comments: self.comments(start),
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_enum(&mut self, scope: &str) -> Result<Enum, Error> {
+        let start = self.pos;
+        self.expect(&Token::Enum)?;
+        let name = match self.next() {
+            Some(Token::Identifier(n)) => n.clone(),
+            _ => return Err(self.error("Expected identifier after 'enum'")),
+        };
+        self.expect(&Token::LeftBrace)?;
+
+        let full_name = if scope.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", scope, name)
+        };
+
+        let mut values = Vec::new();
+        let mut allow_alias = false;
+        let mut options = HashMap::new();
+        let mut reserved_numbers = Vec::new();
+        let mut reserved_names = Vec::new();
+        while let Some(token) = self.peek() {
+            if let Token::RightBrace = token {
+                self.next();
+                break;
+            }
+            self.statement(|p| {
+                match token {
+                    Token::Option => match p.parse_scalar_option()? {
+                        Some((option, value)) if option == "allow_alias" => {
+                            allow_alias = value == "true";
+                        }
+                        Some((option, value)) => {
+                            options.insert(option, value);
+                        }
+                        None => {}
+                    },
+                    Token::Reserved => {
+                        p.next();
+                        if let Some(Token::Literal(_) | Token::Identifier(_)) = p.peek() {
+                            reserved_names.extend(p.parse_reserved_names()?);
+                        } else {
+                            reserved_numbers.extend(p.parse_enum_ranges()?);
+                        }
+                        p.expect(&Token::Semicolon)?;
+                    }
+                    Token::Identifier(value_name) => {
+                        let value_start = p.pos;
+                        let value_name = value_name.clone();
+                        p.next();
+                        p.expect(&Token::Equal)?;
+                        let number =
+                            p.parse_enum_number(&format!("enum value '{}'", value_name))?;
+                        let options = match p.peek() {
+                            Some(Token::LeftBracket) => p.parse_bracket_options()?,
+                            _ => HashMap::new(),
+                        };
+                        p.expect(&Token::Semicolon)?;
+                        values.push(EnumValue {
+                            name: value_name,
+                            number,
+                            options,
+                            comments: p.comments(value_start),
+                        });
+                    }
+                    _ => return Err(p.error_here(format!("Unexpected token in enum: {:?}", token))),
+                }
+                Ok(())
+            });
+        }
+
+        Ok(Enum {
+            name,
+            full_name,
+            values,
+            allow_alias,
+            options,
+            reserved_numbers,
+            reserved_names,
+            comments: self.comments(start),
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_field(&mut self) -> Result<Field, String> {
+    /// Parses a field declared in `scope`. Messages declared by proto2 groups
+    /// are added to `groups`.
+    fn parse_field(&mut self, scope: &str, groups: &mut Vec<Message>) -> Result<Field, Error> {
+        let start = self.pos;
         let mut repeated = false;
-        if let Some(Token::Repeated) = self.peek() {
+        let mut label = None;
+        match self.peek() {
+            Some(Token::Repeated) => repeated = true,
+            Some(Token::Optional) => label = Some(Label::Optional),
+            Some(Token::Required) => label = Some(Label::Required),
+            _ => {}
+        }
+        if repeated || label.is_some() {
             self.next();
-            repeated = true;
+        }
+        if self.editions && label.is_some() {
+            return Err(self.error(
+                "Labels 'optional' and 'required' are not allowed in editions; \
+                 use features.field_presence",
+            ));
         }
 
-        let field_type = match self.next() {
-            Some(Token::Str) => "string".to_string(),
-            Some(Token::Bool) => "bool".to_string(),
-            Some(Token::Int32) => "int32".to_string(),
-            Some(Token::Int64) => "int64".to_string(),
-            Some(Token::Uint32) => "uint32".to_string(),
-            Some(Token::Uint64) => "uint64".to_string(),
-            Some(Token::Sfixed32) => "sfixed32".to_string(),
-            Some(Token::Sfixed64) => "sfixed64".to_string(),
-            Some(Token::Double) => "double".to_string(),
-            Some(Token::Float) => "float".to_string(),
-            Some(Token::Bytes) => "bytes".to_string(),
-            Some(Token::Identifier(t)) => t.clone(),
-            Some(other) => return Err(format!("Unexpected field type: {:?}", other)),
-            None => return Err("Unexpected end of input while reading field type".to_string()),
+        if let Some(Token::Group) = self.peek() {
+            if self.editions {
+                return Err(self.error_here(
+                    "Groups are not allowed in editions; use features.message_encoding",
+                ));
+            }
+            return self.parse_group(start, scope, repeated, label, groups);
+        }
+
+        let mut key_type = None;
+        let field_type = match (self.peek(), self.tokens.get(self.pos + 1).map(|t| &t.token)) {
+            (Some(Token::Identifier(map)), Some(Token::LeftAngle)) if map == "map" => {
+                if repeated || label.is_some() {
+                    return Err(self.error("Map fields cannot have labels"));
+                }
+                self.next();
+                self.next();
+                let key = self.parse_type()?;
+                if !is_map_key_type(&key) {
+                    return Err(self.error(format!("Invalid map key type: {}", key)));
+                }
+                key_type = Some(key);
+                self.expect(&Token::Comma)?;
+                let value = self.parse_type()?;
+                self.expect(&Token::RightAngle)?;
+                value
+            }
+            _ => self.parse_type()?,
         };
 
         let name = match self.next() {
-            Some(Token::Identifier(n)) => n.clone(),
-            Some(other) => return Err(format!("Expected field name, got {:?}", other)),
-            None => return Err("Unexpected end of input while reading field name".to_string()),
+            Some(token) if let Some(name) = name_text(token) => name.to_string(),
+            Some(other) => return Err(self.error(format!("Expected field name, got {:?}", other))),
+            None => return Err(self.error_here("Unexpected end of input while reading field name")),
         };
 
         match self.next() {
             Some(Token::Equal) => {}
-            Some(other) => return Err(format!("Expected '=', got {:?}", other)),
-            None => return Err("Unexpected end of input; expected '='".to_string()),
+            Some(other) => return Err(self.error(format!("Expected '=', got {:?}", other))),
+            None => return Err(self.error_here("Unexpected end of input; expected '='")),
         }
 
-        let number = match self.next() {
-            Some(Token::Number(n)) => *n,
-            Some(other) => return Err(format!("Expected field number, got {:?}", other)),
-            None => return Err("Unexpected end of input; expected field number".to_string()),
+        let number = self.parse_field_number()?;
+
+        let options = match self.peek() {
+            Some(Token::LeftBracket) => self.parse_bracket_options()?,
+            _ => HashMap::new(),
         };
 
         match self.next() {
             Some(Token::Semicolon) => {}
-            Some(other) => return Err(format!("Expected ';', got {:?}", other)),
-            None => return Err("Unexpected end of input; expected ';'".to_string()),
+            Some(other) => return Err(self.error(format!("Expected ';', got {:?}", other))),
+            None => return Err(self.error_here("Unexpected end of input; expected ';'")),
         }
 
         Ok(Field {
@@ -217,74 +778,651 @@ impl<'a> Parser<'a> {
             number,
             field_type,
             repeated,
+            label,
+            key_type,
+            options,
+            group: false,
+            comments: self.comments(start),
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `group Name = N { ... }`, which declares message `Name` and a
+    /// field of that type named `name`.
+    fn parse_group(
+        &mut self,
+        start: usize,
+        scope: &str,
+        repeated: bool,
+        label: Option<Label>,
+        groups: &mut Vec<Message>,
+    ) -> Result<Field, Error> {
+        self.expect(&Token::Group)?;
+        let name = match self.next() {
+            Some(Token::Identifier(n)) if n.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                n.clone()
+            }
+            _ => return Err(self.error("Expected capitalized group name after 'group'")),
+        };
+        self.expect(&Token::Equal)?;
+        let number = self.parse_field_number()?;
+        let options = match self.peek() {
+            Some(Token::LeftBracket) => self.parse_bracket_options()?,
+            _ => HashMap::new(),
+        };
+
+        let span = self.span_from(start);
+        groups.push(self.parse_message_body(start, name.clone(), scope)?);
+
+        Ok(Field {
+            name: name.to_lowercase(),
+            field_type: name,
+            number,
+            repeated,
+            label,
+            key_type: None,
+            options,
+            group: true,
+            comments: self.comments(start),
+            span,
         })
     }
 
-    fn parse_service(&mut self) -> Result<Service, String> {
+    fn parse_field_number(&mut self) -> Result<u32, Error> {
+        match self.next() {
+            Some(Token::Number(n)) => u32::try_from(*n)
+                .map_err(|_| self.error(format!("Field number {} is out of range", n))),
+            Some(other) => Err(self.error(format!("Expected field number, got {:?}", other))),
+            None => Err(self.error_here("Unexpected end of input; expected field number")),
+        }
+    }
+
+    /// Parses the ranges of an enum's `reserved`, like [`Self::parse_ranges`]
+    /// but over enum numbers, which may be negative.
+    fn parse_enum_ranges(&mut self) -> Result<Vec<RangeInclusive<i32>>, Error> {
+        let mut ranges = Vec::new();
+        loop {
+            let start = self.parse_enum_number("reserved range")?;
+            let end = match self.peek() {
+                Some(Token::Identifier(to)) if to == "to" => {
+                    self.next();
+                    match self.peek() {
+                        Some(Token::Identifier(max)) if max == "max" => {
+                            self.next();
+                            i32::MAX
+                        }
+                        _ => self.parse_enum_number("reserved range")?,
+                    }
+                }
+                _ => start,
+            };
+            if end < start {
+                return Err(self.error(format!("Invalid range {} to {}", start, end)));
+            }
+            ranges.push(start..=end);
+
+            match self.peek() {
+                Some(Token::Comma) => {
+                    self.next();
+                }
+                _ => return Ok(ranges),
+            }
+        }
+    }
+
+    /// Parses a possibly negative enum number for `what`, e.g.
+    /// `enum value 'FOO'`.
+    fn parse_enum_number(&mut self, what: &str) -> Result<i32, Error> {
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.next();
+        }
+        match self.next() {
+            Some(Token::Number(n)) => i64::try_from(*n)
+                .ok()
+                .and_then(|n| i32::try_from(if negative { -n } else { n }).ok())
+                .ok_or_else(|| self.error(format!("Number for {} is out of range", what))),
+            other => Err(self.error(format!("Expected number for {}, got {:?}", what, other))),
+        }
+    }
+
+    /// Parses `[name = value, ...]` after a field or enum value. Aggregate
+    /// values are skipped, as for options elsewhere.
+    fn parse_bracket_options(&mut self) -> Result<HashMap<String, String>, Error> {
+        self.expect(&Token::LeftBracket)?;
+
+        let mut options = HashMap::new();
+        loop {
+            let start = self.pos;
+            let name = self.parse_option_name()?;
+            self.expect(&Token::Equal)?;
+            match self.peek() {
+                Some(Token::LeftBrace) => {
+                    self.parse_aggregate(false)?;
+                    self.lossy(
+                        start,
+                        format!("drop the aggregate value of option '{}'", name),
+                    );
+                }
+                // A default is printed the way the field's type is written.
+                _ if name == "default" => {
+                    options.insert(name, self.parse_constant()?);
+                }
+                _ => {
+                    let value = self.parse_scalar(&name)?;
+                    options.insert(name, value);
+                }
+            }
+
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::RightBracket) => return Ok(options),
+                other => {
+                    return Err(self.error(format!("Expected ',' or ']', got {:?}", other)));
+                }
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<String, Error> {
+        let field_type = match self.next() {
+            Some(Token::Identifier(_)) | Some(Token::Dot) => {
+                self.pos -= 1;
+                self.parse_type_reference()?
+            }
+            Some(other) => match scalar_type_name(other) {
+                Some(name) => name.to_string(),
+                None => return Err(self.error(format!("Unexpected field type: {:?}", other))),
+            },
+            None => return Err(self.error_here("Unexpected end of input while reading field type")),
+        };
+
+        Ok(field_type)
+    }
+
+    /// Parses a message or enum reference such as `Inner`, `common.v1.Money`
+    /// or `.google.protobuf.Empty`. Fully-qualified references keep their
+    /// leading dot so they are never resolved relative to a scope.
+    fn parse_type_reference(&mut self) -> Result<String, Error> {
+        if let Some(Token::Dot) = self.peek() {
+            self.next();
+            return Ok(format!(".{}", self.parse_dotted_identifier()?));
+        }
+        self.parse_dotted_identifier()
+    }
+
+    fn parse_service(&mut self) -> Result<Service, Error> {
+        let start = self.pos;
         self.expect(&Token::Service)?;
         let name = match self.next() {
             Some(Token::Identifier(n)) => n.clone(),
-            _ => return Err("Expected identifier after 'service'".into()),
+            _ => return Err(self.error("Expected identifier after 'service'")),
         };
         self.expect(&Token::LeftBrace)?;
 
         let mut methods = HashMap::new();
         while let Some(token) = self.peek() {
-            match token {
-                Token::RightBrace => {
-                    self.next();
-                    break;
-                }
-                Token::Rpc => {
-                    let method = self.parse_rpc()?;
-                    if methods.contains_key(&method.name) {
-                        return Err(format!("Duplicate RPC method: {}", method.name));
+            if let Token::RightBrace = token {
+                self.next();
+                break;
+            }
+            self.statement(|p| {
+                match token {
+                    Token::Rpc => {
+                        let method = p.parse_rpc()?;
+                        if methods.contains_key(&method.name) {
+                            return Err(p.error(format!("Duplicate RPC method: {}", method.name)));
+                        }
+                        methods.insert(method.name.clone(), method);
+                    }
+                    _ => {
+                        return Err(
+                            p.error_here(format!("Unexpected token in service: {:?}", token))
+                        );
                     }
-                    methods.insert(method.name.clone(), method);
                 }
-                _ => return Err(format!("Unexpected token in service: {:?}", token)),
-            }
+                Ok(())
+            });
         }
 
-        Ok(Service { name, methods })
+        Ok(Service {
+            name,
+            methods,
+            comments: self.comments(start),
+            span: self.span_from(start),
+        })
     }
 
-    fn parse_rpc(&mut self) -> Result<RpcMethod, String> {
+    fn parse_rpc(&mut self) -> Result<RpcMethod, Error> {
+        let start = self.pos;
         self.expect(&Token::Rpc)?;
         let name = match self.next() {
             Some(Token::Identifier(n)) => n.clone(),
-            _ => return Err("Expected identifier after 'rpc'".into()),
+            _ => return Err(self.error("Expected identifier after 'rpc'")),
         };
 
         self.expect(&Token::LeftParen)?;
-        let req_type = match self.next() {
-            Some(Token::Identifier(t)) => t.clone(),
-            _ => return Err("Expected request type".into()),
-        };
+        let client_streaming = self.parse_stream_keyword();
+        let req_type = self
+            .parse_type_reference()
+            .map_err(|_| self.error("Expected request type"))?;
         self.expect(&Token::RightParen)?;
 
         self.expect(&Token::Returns)?;
         self.expect(&Token::LeftParen)?;
-        let res_type = match self.next() {
-            Some(Token::Identifier(t)) => t.clone(),
-            _ => return Err("Expected response type".into()),
-        };
+        let server_streaming = self.parse_stream_keyword();
+        let res_type = self
+            .parse_type_reference()
+            .map_err(|_| self.error("Expected response type"))?;
         self.expect(&Token::RightParen)?;
-        self.expect(&Token::Semicolon)?;
+        let span = self.span_from(start);
+
+        let mut options = HashMap::new();
+        let mut http = None;
+        match self.next() {
+            Some(Token::Semicolon) => {}
+            Some(Token::LeftBrace) => {
+                while let Some(token) = self.peek() {
+                    match token {
+                        Token::RightBrace => {
+                            self.next();
+                            break;
+                        }
+                        Token::Option => {
+                            let start = self.pos;
+                            match self.parse_option()? {
+                                (option, OptionValue::Aggregate(fields))
+                                    if option == "(google.api.http)" =>
+                                {
+                                    let rule =
+                                        parse_http_rule(&fields).map_err(|e| self.error(e))?;
+                                    if let Some(field) = dropped_http_field(&fields) {
+                                        self.lossy(
+                                            start,
+                                            format!("drop field '{}' of option {}", field, option),
+                                        );
+                                    }
+                                    http = Some(rule);
+                                }
+                                (option, value) => {
+                                    if let Some(name) = flatten_option(option, value, &mut options)
+                                    {
+                                        self.lossy(
+                                            start,
+                                            format!(
+                                                "keep only the last value of option '{}'",
+                                                name
+                                            ),
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        Token::Semicolon => {
+                            self.next();
+                        }
+                        _ => {
+                            return Err(self
+                                .error_here(format!("Unexpected token in rpc body: {:?}", token)));
+                        }
+                    }
+                }
+                if let Some(Token::Semicolon) = self.peek() {
+                    self.next();
+                }
+            }
+            other => {
+                return Err(self.error(format!("Expected ';' or '{{' after rpc, got {:?}", other)));
+            }
+        }
 
         Ok(RpcMethod {
             name,
             request: req_type,
             response: res_type,
+            client_streaming,
+            server_streaming,
+            options,
+            http,
+            comments: self.comments(start),
+            span,
         })
     }
+
+    /// `stream` is contextual: it only marks a stream when another type name
+    /// follows it, so a message named `stream` still parses.
+    fn parse_stream_keyword(&mut self) -> bool {
+        match (self.peek(), self.tokens.get(self.pos + 1).map(|t| &t.token)) {
+            (Some(Token::Identifier(word)), Some(Token::Identifier(_) | Token::Dot))
+                if word == "stream" =>
+            {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_option(&mut self) -> Result<(String, OptionValue), Error> {
+        self.expect(&Token::Option)?;
+        let name = self.parse_option_name()?;
+        self.expect(&Token::Equal)?;
+
+        let value = match self.peek() {
+            // `google.api.http` fields are always printed as strings.
+            Some(Token::LeftBrace) => {
+                OptionValue::Aggregate(self.parse_aggregate(name != "(google.api.http)")?)
+            }
+            _ => OptionValue::Scalar(self.parse_scalar(&name)?),
+        };
+        self.expect(&Token::Semicolon)?;
+
+        Ok((name, value))
+    }
+
+    /// Parses an option statement outside an RPC, where aggregate values are
+    /// skipped.
+    fn parse_scalar_option(&mut self) -> Result<Option<(String, String)>, Error> {
+        let start = self.pos;
+        match self.parse_option()? {
+            (name, OptionValue::Scalar(value)) => Ok(Some((name, value))),
+            (name, OptionValue::Aggregate(_)) => {
+                self.lossy(
+                    start,
+                    format!("drop the aggregate value of option '{}'", name),
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Parses `name`, `(extension.name)` or `(extension.name).field`.
+    fn parse_option_name(&mut self) -> Result<String, Error> {
+        let mut name = match self.next() {
+            Some(Token::Identifier(n)) => n.clone(),
+            Some(Token::LeftParen) => {
+                let extension = self.parse_type_reference()?;
+                self.expect(&Token::RightParen)?;
+                format!("({})", extension)
+            }
+            other => return Err(self.error(format!("Expected option name, got {:?}", other))),
+        };
+
+        while let Some(Token::Dot) = self.peek() {
+            self.next();
+            // Option fields may share a name with a scalar type, as in
+            // `(validate.rules).string`.
+            let part = self
+                .next()
+                .and_then(name_text)
+                .ok_or_else(|| self.error("Expected identifier after '.' in option name"))?;
+            name.push('.');
+            name.push_str(part);
+        }
+
+        Ok(name)
+    }
+
+    /// Parses a constant: a string (adjacent strings are concatenated), an
+    /// identifier or a number with an optional sign. Numbers are kept in
+    /// decimal.
+    fn parse_constant(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some(Token::Literal(s)) => {
+                let mut value = s.clone();
+                while let Some(Token::Literal(s)) = self.peek() {
+                    value.push_str(s);
+                    self.next();
+                }
+                Ok(value)
+            }
+            Some(Token::Bool) => Ok("true".to_string()),
+            Some(Token::Identifier(s)) => Ok(s.clone()),
+            Some(Token::Number(n)) => Ok(n.to_string()),
+            Some(Token::FloatLiteral(f)) => Ok(format!("{:?}", f)),
+            Some(sign @ (Token::Minus | Token::Plus)) => {
+                let sign = if *sign == Token::Minus { "-" } else { "" };
+                match self.next() {
+                    Some(Token::Number(n)) => Ok(format!("{}{}", sign, n)),
+                    Some(Token::FloatLiteral(f)) => Ok(format!("{}{:?}", sign, f)),
+                    Some(Token::Identifier(s)) if s == "inf" || s == "nan" => {
+                        Ok(format!("{}{}", sign, s))
+                    }
+                    other => {
+                        Err(self.error(format!("Expected number after sign, got {:?}", other)))
+                    }
+                }
+            }
+            Some(token) => {
+                Err(self.error(format!("Unexpected token for option value: {:?}", token)))
+            }
+            None => Err(self.error_here("Expected option value after '='")),
+        }
+    }
+
+    /// Parses the constant value of option `name`, checking when parsing
+    /// [`Parser::lossless`]ly that the printer quotes it the way it is
+    /// written.
+    fn parse_scalar(&mut self, name: &str) -> Result<String, Error> {
+        let start = self.pos;
+        let quoted = matches!(self.peek(), Some(Token::Literal(_)));
+        let value = self.parse_constant()?;
+        match (quoted, printer::is_bare(name, &value)) {
+            (true, true) => self.lossy(start, format!("print \"{}\" without quotes", value)),
+            (false, false) => self.lossy(start, format!("print {} as a string", value)),
+            _ => {}
+        }
+        Ok(value)
+    }
+
+    /// Parses a text-format message literal. Field separators (`,` or `;`)
+    /// and the `:` before a nested message are optional. With `checked`,
+    /// constants are parsed with [`Parser::parse_scalar`].
+    fn parse_aggregate(&mut self, checked: bool) -> Result<Vec<(String, OptionValue)>, Error> {
+        self.expect(&Token::LeftBrace)?;
+
+        let mut fields = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::RightBrace) => break,
+                Some(Token::Comma) | Some(Token::Semicolon) => {}
+                Some(Token::Identifier(key)) => {
+                    let key = key.clone();
+                    if let Some(Token::Colon) = self.peek() {
+                        self.next();
+                    }
+                    let value = match self.peek() {
+                        Some(Token::LeftBrace) => {
+                            OptionValue::Aggregate(self.parse_aggregate(checked)?)
+                        }
+                        // Aggregate fields are printed as options of their own,
+                        // which are never string options.
+                        _ if checked => OptionValue::Scalar(self.parse_scalar("")?),
+                        _ => OptionValue::Scalar(self.parse_constant()?),
+                    };
+                    fields.push((key, value));
+                }
+                other => {
+                    return Err(
+                        self.error(format!("Unexpected token in option value: {:?}", other))
+                    );
+                }
+            }
+        }
+
+        Ok(fields)
+    }
+}
+
+/// Stores an option value in `options`, keying each field of an aggregate
+/// value by its path: `(acme.auth) = { required: true }` becomes
+/// `(acme.auth).required`. Returns the last key set more than once, as a
+/// repeated field is, whose earlier values are overwritten.
+fn flatten_option(
+    name: String,
+    value: OptionValue,
+    options: &mut HashMap<String, String>,
+) -> Option<String> {
+    match value {
+        OptionValue::Scalar(value) => options.insert(name.clone(), value).map(|_| name),
+        OptionValue::Aggregate(fields) => {
+            let mut repeated = None;
+            for (field, value) in fields {
+                let name = format!("{}.{}", name, field);
+                repeated = flatten_option(name, value, options).or(repeated);
+            }
+            repeated
+        }
+    }
+}
+
+/// The first field of a `google.api.http` option, at any depth, that
+/// [`HttpRule`] doesn't hold, such as `response_body`.
+fn dropped_http_field(fields: &[(String, OptionValue)]) -> Option<&str> {
+    fields
+        .iter()
+        .find_map(|(key, value)| match (key.as_str(), value) {
+            ("additional_bindings", OptionValue::Aggregate(binding)) => dropped_http_field(binding),
+            ("get" | "put" | "post" | "delete" | "patch" | "body", OptionValue::Scalar(_)) => None,
+            ("custom", OptionValue::Aggregate(custom)) => custom
+                .iter()
+                .find(|(key, _)| key != "kind" && key != "path")
+                .map(|(key, _)| key.as_str()),
+            (key, _) => Some(key),
+        })
+}
+
+/// Builds an [`HttpRule`] from the fields of a `google.api.http` option.
+fn parse_http_rule(fields: &[(String, OptionValue)]) -> Result<HttpRule, String> {
+    let mut pattern = None;
+    let mut body = None;
+    let mut additional_bindings = Vec::new();
+
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("get" | "put" | "post" | "delete" | "patch", OptionValue::Scalar(path)) => {
+                pattern = Some((key.to_uppercase(), path.clone()));
+            }
+            ("custom", OptionValue::Aggregate(custom)) => {
+                let mut kind = None;
+                let mut path = None;
+                for (key, value) in custom {
+                    match (key.as_str(), value) {
+                        ("kind", OptionValue::Scalar(v)) => kind = Some(v.to_uppercase()),
+                        ("path", OptionValue::Scalar(v)) => path = Some(v.clone()),
+                        _ => {}
+                    }
+                }
+                match (kind, path) {
+                    (Some(kind), Some(path)) => pattern = Some((kind, path)),
+                    _ => return Err("Custom http pattern requires 'kind' and 'path'".into()),
+                }
+            }
+            ("body", OptionValue::Scalar(field)) => body = Some(field.clone()),
+            ("additional_bindings", OptionValue::Aggregate(binding)) => {
+                additional_bindings.push(parse_http_rule(binding)?);
+            }
+            _ => {}
+        }
+    }
+
+    let (method, path) = pattern.ok_or("google.api.http option has no HTTP method")?;
+
+    Ok(HttpRule {
+        method,
+        path,
+        body,
+        additional_bindings,
+    })
+}
+
+/// Whether `token` ends a statement, so a comment after it on the same line
+/// trails that statement.
+fn ends_statement(token: &Token) -> bool {
+    matches!(token, Token::Semicolon | Token::RightBrace)
+}
+
+/// The text of a token used as a name. Keywords only have their meaning
+/// where a statement or type is expected, so `bool required = 1;` and
+/// `(acme.auth.required)` are valid.
+fn name_text(token: &Token) -> Option<&str> {
+    let keyword = match token {
+        Token::Identifier(name) => return Some(name),
+        Token::Message => "message",
+        Token::Enum => "enum",
+        Token::Oneof => "oneof",
+        Token::Service => "service",
+        Token::Rpc => "rpc",
+        Token::Returns => "returns",
+        Token::Option => "option",
+        Token::Repeated => "repeated",
+        Token::Optional => "optional",
+        Token::Required => "required",
+        Token::Group => "group",
+        Token::Package => "package",
+        Token::Import => "import",
+        Token::Syntax => "syntax",
+        Token::Reserved => "reserved",
+        Token::Extensions => "extensions",
+        token => return scalar_type_name(token),
+    };
+    Some(keyword)
+}
+
+/// The proto name of a scalar type keyword.
+fn scalar_type_name(token: &Token) -> Option<&'static str> {
+    let name = match token {
+        Token::Str => "string",
+        Token::Bool => "bool",
+        Token::Int32 => "int32",
+        Token::Int64 => "int64",
+        Token::Uint32 => "uint32",
+        Token::Uint64 => "uint64",
+        Token::Sint32 => "sint32",
+        Token::Sint64 => "sint64",
+        Token::Fixed32 => "fixed32",
+        Token::Fixed64 => "fixed64",
+        Token::Sfixed32 => "sfixed32",
+        Token::Sfixed64 => "sfixed64",
+        Token::Double => "double",
+        Token::Float => "float",
+        Token::Bytes => "bytes",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Map keys may be any integral or string scalar; floats, bytes and named
+/// types are rejected by protoc.
+fn is_map_key_type(key: &str) -> bool {
+    matches!(
+        key,
+        "int32"
+            | "int64"
+            | "uint32"
+            | "uint64"
+            | "sint32"
+            | "sint64"
+            | "fixed32"
+            | "fixed64"
+            | "sfixed32"
+            | "sfixed64"
+            | "bool"
+            | "string"
+    )
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::{
+        error::Error,
         lexer::Lexer,
-        model::{Field, Proto, RpcMethod},
+        model::{
+            Comments, Definition, EnumValue, Field, FieldPresence, HttpRule, Label, Proto,
+            RpcMethod,
+        },
         parser::Parser,
     };
 
@@ -345,6 +1483,12 @@ mod tests {
                 field_type: "string".into(),
                 number: 1,
                 repeated: false,
+                label: None,
+                key_type: None,
+                options: HashMap::new(),
+                group: false,
+                comments: Comments::default(),
+                span: msg.fields[0].span,
             }
         );
         assert_eq!(
@@ -354,8 +1498,488 @@ mod tests {
                 field_type: "string".into(),
                 number: 3,
                 repeated: true,
+                label: None,
+                key_type: None,
+                options: HashMap::new(),
+                group: false,
+                comments: Comments::default(),
+                span: msg.fields[2].span,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_messages() {
+        let proto = parse_input(
+            r#"
+            package shop;
+
+            message Order {
+                message Item {
+                    message Price {
+                        int64 amount = 1;
+                    }
+                    string sku = 1;
+                    Price price = 2;
+                }
+                repeated Item items = 1;
+                Order.Item featured = 2;
+            }
+        "#,
+        );
+
+        assert_eq!(proto.messages.len(), 1);
+        let order = &proto.messages[0];
+        assert_eq!(order.full_name, "shop.Order");
+        assert_eq!(order.messages.len(), 1);
+        assert_eq!(order.messages[0].full_name, "shop.Order.Item");
+        assert_eq!(
+            order.messages[0].messages[0].full_name,
+            "shop.Order.Item.Price"
+        );
+        assert_eq!(order.fields[1].field_type, "Order.Item");
+
+        let price = proto.find_message("shop.Order.Item.Price").unwrap();
+        assert_eq!(price.name, "Price");
+        assert_eq!(proto.relative_name(&price.full_name), "Order.Item.Price");
+    }
+
+    #[test]
+    fn test_resolve_nested_message_from_scope() {
+        let proto = parse_input(
+            r#"
+            package shop;
+
+            message Item { string sku = 1; }
+
+            message Order {
+                message Item { string name = 1; }
+                Item item = 1;
+            }
+        "#,
+        );
+
+        let inner = proto.resolve_message("shop.Order", "Item").unwrap();
+        assert_eq!(inner.full_name, "shop.Order.Item");
+
+        let outer = proto.resolve_message("shop", "Item").unwrap();
+        assert_eq!(outer.full_name, "shop.Item");
+
+        let qualified = proto.resolve_message("shop", "Order.Item").unwrap();
+        assert_eq!(qualified.full_name, "shop.Order.Item");
+
+        assert!(proto.resolve_message("shop.Order", "Missing").is_none());
+    }
+
+    #[test]
+    fn test_parse_map_fields() {
+        let proto = parse_input(
+            r#"
+            message Book {
+                map<string, int64> labels = 1;
+                map<int32, Author> authors = 2;
+                string map = 3;
+            }
+        "#,
+        );
+
+        let book = &proto.messages[0];
+        assert_eq!(
+            book.fields[0],
+            Field {
+                name: "labels".into(),
+                field_type: "int64".into(),
+                number: 1,
+                repeated: false,
+                label: None,
+                key_type: Some("string".into()),
+                options: HashMap::new(),
+                group: false,
+                comments: Comments::default(),
+                span: book.fields[0].span,
+            }
+        );
+        assert_eq!(book.fields[1].key_type, Some("int32".into()));
+        assert_eq!(book.fields[1].field_type, "Author");
+        assert_eq!(book.fields[2].name, "map");
+        assert_eq!(book.fields[2].key_type, None);
+    }
+
+    #[test]
+    fn test_reject_invalid_map_fields() {
+        let lexer = Lexer::new();
+        for input in [
+            "message M { map<double, string> m = 1; }",
+            "message M { repeated map<string, string> m = 1; }",
+            "message M { map<string string> m = 1; }",
+        ] {
+            let tokens = lexer.lex(input).unwrap();
+            assert!(Parser::new(&tokens).parse().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_field_and_message_options() {
+        let proto = parse_input(
+            r#"
+            message Book {
+                option deprecated = true;
+                reserved 2, 15, 9 to 11;
+                reserved "isbn", "author";
+                extensions 100 to max;
+
+                string book_id = 1 [json_name = "bookId", deprecated = true];
+                string title = 3 [(validate.rules).string = { min_len: 1 }];
+            }
+
+            enum Shelf {
+                SHELF_UNSPECIFIED = 0;
+                SHELF_OLD = 1 [deprecated = true];
             }
+        "#,
         );
+
+        let book = &proto.messages[0];
+        assert_eq!(book.options["deprecated"], "true");
+        assert_eq!(book.reserved_numbers, vec![2..=2, 15..=15, 9..=11]);
+        assert_eq!(book.reserved_names, vec!["isbn", "author"]);
+        assert_eq!(book.extensions, vec![100..=536_870_911]);
+
+        assert_eq!(book.fields[0].json_name(), Some("bookId"));
+        assert!(book.fields[0].deprecated());
+        assert_eq!(book.fields[1].json_name(), None);
+        assert!(!book.fields[1].deprecated());
+        assert_eq!(proto.enums[0].values.len(), 2);
+    }
+
+    #[test]
+    fn test_reject_invalid_reserved_ranges() {
+        let lexer = Lexer::new();
+        for input in [
+            "message M { reserved 5 to 2; }",
+            "message M { reserved 1 to; }",
+            "message M { string a = 1 [deprecated = true; }",
+        ] {
+            let tokens = lexer.lex(input).unwrap();
+            assert!(Parser::new(&tokens).parse().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_enum_reserved() {
+        let proto = parse_input(
+            r#"
+            enum Status {
+                reserved 2, 15 to 20, -5 to -1, 100 to max;
+                reserved "FOO", "BAR";
+                STATUS_UNSPECIFIED = 0;
+            }
+        "#,
+        );
+
+        let status = &proto.enums[0];
+        assert_eq!(
+            status.reserved_numbers,
+            vec![2..=2, 15..=20, -5..=-1, 100..=i32::MAX]
+        );
+        assert_eq!(status.reserved_names, vec!["FOO", "BAR"]);
+        assert_eq!(status.values.len(), 1);
+
+        let lexer = Lexer::new();
+        let tokens = lexer.lex("enum E { reserved 3 to 1; }").unwrap();
+        assert!(Parser::new(&tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_oneof() {
+        let proto = parse_input(
+            r#"
+            message Payment {
+                string id = 1;
+                oneof method {
+                    Card card = 2;
+                    string voucher_code = 3;
+                }
+            }
+        "#,
+        );
+
+        let payment = &proto.messages[0];
+        assert_eq!(payment.fields.len(), 1);
+        assert_eq!(payment.oneofs.len(), 1);
+        assert_eq!(payment.oneofs[0].name, "method");
+        assert_eq!(payment.oneofs[0].fields[0].field_type, "Card");
+        assert_eq!(payment.oneofs[0].fields[1].name, "voucher_code");
+        assert_eq!(payment.oneofs[0].fields[1].number, 3);
+    }
+
+    #[test]
+    fn test_reject_repeated_oneof_member() {
+        let lexer = Lexer::new();
+        let tokens = lexer
+            .lex("message M { oneof kind { repeated string tags = 1; } }")
+            .unwrap();
+        assert!(Parser::new(&tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_proto2_labels_defaults_and_groups() {
+        let proto = parse_input(
+            r#"
+            syntax = "proto2";
+            package search;
+
+            message SearchRequest {
+                required string query = 1;
+                optional int32 page = 2 [default = 10];
+                repeated group Result = 3 {
+                    required string url = 1;
+                    optional string title = 2;
+                }
+            }
+        "#,
+        );
+        assert!(proto.is_proto2());
+
+        let request = &proto.messages[0];
+        assert_eq!(request.fields[0].label, Some(Label::Required));
+        assert_eq!(request.fields[1].label, Some(Label::Optional));
+        assert_eq!(request.fields[1].default_value(), Some("10"));
+        let presence = |field: &Field| proto.field_presence("search.SearchRequest", field);
+        assert_eq!(presence(&request.fields[0]), FieldPresence::LegacyRequired);
+        assert_eq!(presence(&request.fields[1]), FieldPresence::Explicit);
+
+        let result = &request.fields[2];
+        assert_eq!(result.name, "result");
+        assert_eq!(result.field_type, "Result");
+        assert_eq!(result.number, 3);
+        assert!(result.repeated);
+        assert_eq!(request.messages[0].full_name, "search.SearchRequest.Result");
+        assert_eq!(request.messages[0].fields.len(), 2);
+
+        let proto = parse_input(
+            r#"
+            syntax = "proto3";
+            message M { optional string nickname = 1; }
+        "#,
+        );
+        assert!(!proto.is_proto2());
+        assert_eq!(proto.messages[0].fields[0].label, Some(Label::Optional));
+        let nickname = &proto.messages[0].fields[0];
+        assert_eq!(proto.field_presence("M", nickname), FieldPresence::Explicit);
+        assert!(parse_input("message M {}").is_proto2());
+    }
+
+    #[test]
+    fn test_parse_editions() {
+        let proto = parse_input(
+            r#"
+            edition = "2023";
+            package shop;
+            option features.field_presence = IMPLICIT;
+
+            message Order {
+                string id = 1;
+                string note = 2 [features.field_presence = EXPLICIT];
+                reserved legacy_id;
+
+                message Line {
+                    option features.field_presence = EXPLICIT;
+                    int32 quantity = 1;
+                    string sku = 2 [features.field_presence = LEGACY_REQUIRED];
+                }
+            }
+        "#,
+        );
+        assert_eq!(proto.edition.as_deref(), Some("2023"));
+        assert_eq!(proto.syntax, None);
+        assert!(!proto.is_proto2());
+
+        let order = &proto.messages[0];
+        let presence = |message: &str, field: &Field| proto.field_presence(message, field);
+        assert_eq!(order.reserved_names, vec!["legacy_id"]);
+        assert_eq!(
+            presence("shop.Order", &order.fields[0]),
+            FieldPresence::Implicit
+        );
+        assert_eq!(
+            presence("shop.Order", &order.fields[1]),
+            FieldPresence::Explicit
+        );
+        let line = &order.messages[0];
+        assert_eq!(
+            presence(".shop.Order.Line", &line.fields[0]),
+            FieldPresence::Explicit
+        );
+        assert_eq!(
+            presence("shop.Order.Line", &line.fields[1]),
+            FieldPresence::LegacyRequired
+        );
+
+        let proto = parse_input("edition = \"2023\"; message M { string name = 1; }");
+        let name = &proto.messages[0].fields[0];
+        assert_eq!(proto.field_presence("M", name), FieldPresence::Explicit);
+
+        let lexer = Lexer::new();
+        for input in [
+            "edition = \"2023\"; message M { optional string name = 1; }",
+            "edition = \"2023\"; message M { repeated group Result = 1 {} }",
+        ] {
+            let tokens = lexer.lex(input).unwrap();
+            assert!(Parser::new(&tokens).parse().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_literal_constants() {
+        let proto = parse_input(
+            r#"
+            syntax = "proto2";
+            option java_package = "com." 'example';
+
+            enum Level {
+                LEVEL_LOW = -1;
+                LEVEL_HIGH = 0x10;
+            }
+
+            message Reading {
+                optional double value = 017 [default = -1.5];
+                optional float ratio = 2 [default = -inf];
+                optional sint64 delta = 3 [default = -0x10];
+                optional string unit = 4 [default = 'm²'];
+            }
+        "#,
+        );
+
+        assert_eq!(proto.options["java_package"], "com.example");
+        let values: Vec<i32> = proto.enums[0].values.iter().map(|v| v.number).collect();
+        assert_eq!(values, vec![-1, 16]);
+
+        let fields = &proto.messages[0].fields;
+        assert_eq!(fields[0].number, 15);
+        assert_eq!(fields[0].default_value(), Some("-1.5"));
+        assert_eq!(fields[1].default_value(), Some("-inf"));
+        assert_eq!(fields[2].default_value(), Some("-16"));
+        assert_eq!(fields[3].default_value(), Some("m²"));
+
+        let lexer = Lexer::new();
+        let tokens = lexer.lex("enum E { A = -3000000000; }").unwrap();
+        assert!(Parser::new(&tokens).parse().is_err());
+        let tokens = lexer.lex("message M { string s = 4294967296; }").unwrap();
+        assert!(Parser::new(&tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let lexer = Lexer::new();
+        let tokens = lexer.lex("message M {\n  string name = 1\n}").unwrap();
+        let error = Parser::new(&tokens).parse().unwrap_err();
+        assert_eq!(error.message(), "Expected ';', got RightBrace");
+        let span = error.span().unwrap();
+        assert_eq!((span.line, span.column), (3, 1));
+
+        let tokens = lexer.lex("message M { string name =").unwrap();
+        let span = Parser::new(&tokens).parse().unwrap_err().span().unwrap();
+        assert_eq!((span.start, span.line, span.column), (25, 1, 26));
+    }
+
+    #[test]
+    fn test_recover_from_syntax_errors() {
+        let input = r#"
+            syntax = "proto3";
+            message M {
+              string name = ;
+              int32 id = 2;
+              option (acme.limits) = { burst: ; };
+              bool ok = 3
+            }
+            enum E { A = x; B = 1; }
+            }
+            service S {
+              rpc Get (M) returns M;
+              rpc List (M) returns (M);
+            }
+        "#;
+        let tokens = Lexer::new().lex(input).unwrap();
+        let (proto, errors) = Parser::new(&tokens).parse_partial();
+
+        let messages: Vec<_> = errors.iter().map(Error::message).collect();
+        assert_eq!(
+            messages,
+            [
+                "Expected field number, got Semicolon",
+                "Unexpected token for option value: Semicolon",
+                "Expected ';', got RightBrace",
+                "Expected number for enum value 'A', got Some(Identifier(\"x\"))",
+                "Unexpected token: RightBrace",
+                "Expected LeftParen, found Identifier(\"M\")",
+            ]
+        );
+        let lines: Vec<_> = errors.iter().map(|e| e.span().unwrap().line).collect();
+        assert_eq!(lines, [4, 6, 8, 9, 10, 12]);
+
+        let fields: Vec<_> = proto.messages[0].fields.iter().map(|f| &f.name).collect();
+        assert_eq!(fields, ["id"]);
+        let values: Vec<_> = proto.enums[0].values.iter().map(|v| &v.name).collect();
+        assert_eq!(values, ["B"]);
+        assert!(proto.services[0].methods.contains_key("List"));
+
+        assert!(matches!(
+            Parser::new(&tokens).parse(),
+            Err(Error::Multiple(errors)) if errors.len() == 6
+        ));
+    }
+
+    #[test]
+    fn test_parse_enums() {
+        let proto = parse_input(
+            r#"
+            package auth;
+
+            enum Role {
+                option allow_alias = true;
+                ROLE_UNSPECIFIED = 0;
+                ROLE_ADMIN = 1;
+                ROLE_ROOT = 1;
+            }
+
+            message User {
+                enum Status {
+                    STATUS_UNSPECIFIED = 0;
+                    STATUS_ACTIVE = 1;
+                }
+                Role role = 1;
+                Status status = 2;
+            }
+        "#,
+        );
+
+        assert_eq!(proto.enums.len(), 1);
+        let role = &proto.enums[0];
+        assert_eq!(role.full_name, "auth.Role");
+        assert!(role.allow_alias);
+        assert_eq!(
+            role.values[2],
+            EnumValue {
+                name: "ROLE_ROOT".into(),
+                number: 1,
+                options: HashMap::new(),
+                comments: Comments::default(),
+            }
+        );
+
+        let user = &proto.messages[0];
+        assert_eq!(user.enums[0].full_name, "auth.User.Status");
+        assert!(!user.enums[0].allow_alias);
+
+        match proto.resolve(&user.full_name, "Status") {
+            Some(Definition::Enum(e)) => assert_eq!(e.full_name, "auth.User.Status"),
+            other => panic!("Expected enum, got {:?}", other),
+        }
+        match proto.resolve(&user.full_name, "Role") {
+            Some(Definition::Enum(e)) => assert_eq!(e.full_name, "auth.Role"),
+            other => panic!("Expected enum, got {:?}", other),
+        }
     }
 
     #[test]
@@ -373,14 +1997,228 @@ mod tests {
         let svc = &proto.services[0];
         assert_eq!(svc.name, "AuthService");
         assert_eq!(svc.methods.len(), 2);
+        let login = &svc.methods["Login"];
         assert_eq!(
-            svc.methods.get("Login"),
-            Some(&RpcMethod {
+            login,
+            &RpcMethod {
                 name: "Login".into(),
                 request: "LoginRequest".into(),
                 response: "LoginResponse".into(),
+                client_streaming: false,
+                server_streaming: false,
+                options: HashMap::new(),
+                http: None,
+                comments: Comments::default(),
+                span: login.span,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_streaming_rpcs() {
+        let proto = parse_input(
+            r#"
+            service Chat {
+                rpc Watch (WatchRequest) returns (stream Event);
+                rpc Upload (stream Chunk) returns (UploadResponse) {}
+                rpc Talk (stream Message) returns (stream Message) {
+                    option deprecated = true;
+                };
+                rpc Legacy (stream) returns (Ack);
+            }
+        "#,
+        );
+
+        let svc = &proto.services[0];
+        let watch = &svc.methods["Watch"];
+        assert!(!watch.client_streaming);
+        assert!(watch.server_streaming);
+        assert_eq!(watch.response, "Event");
+
+        let upload = &svc.methods["Upload"];
+        assert!(upload.client_streaming);
+        assert!(!upload.server_streaming);
+
+        let talk = &svc.methods["Talk"];
+        assert!(talk.client_streaming && talk.server_streaming);
+        assert_eq!(talk.options.get("deprecated"), Some(&"true".to_string()));
+
+        let legacy = &svc.methods["Legacy"];
+        assert!(!legacy.client_streaming);
+        assert_eq!(legacy.request, "stream");
+    }
+
+    #[test]
+    fn test_parse_google_api_http_annotations() {
+        let proto = parse_input(
+            r#"
+            service Books {
+                rpc Get (GetRequest) returns (Book) {
+                    option (google.api.http) = { get: "/v1/books/{id}" };
+                }
+                rpc Update (UpdateRequest) returns (Book) {
+                    option (google.api.http) = {
+                        patch: "/v1/books/{book.id}"
+                        body: "book"
+                        additional_bindings {
+                            put: "/v1/books/{book.id}",
+                            body: "*"
+                        }
+                    };
+                }
+                rpc Head (GetRequest) returns (Book) {
+                    option (google.api.http) = {
+                        custom: { kind: "head" path: "/v1/books/{id}" }
+                    };
+                }
+            }
+        "#,
+        );
+
+        let svc = &proto.services[0];
+        assert_eq!(
+            svc.methods["Get"].http,
+            Some(HttpRule {
+                method: "GET".into(),
+                path: "/v1/books/{id}".into(),
+                body: None,
+                additional_bindings: Vec::new(),
             })
         );
+
+        let update = svc.methods["Update"].http.as_ref().unwrap();
+        assert_eq!(update.method, "PATCH");
+        assert_eq!(update.body, Some("book".into()));
+        assert_eq!(update.additional_bindings.len(), 1);
+        assert_eq!(update.additional_bindings[0].method, "PUT");
+        assert_eq!(update.additional_bindings[0].body, Some("*".into()));
+
+        let head = svc.methods["Head"].http.as_ref().unwrap();
+        assert_eq!(head.method, "HEAD");
+        assert_eq!(head.path, "/v1/books/{id}");
+    }
+
+    #[test]
+    fn test_parse_extend_blocks() {
+        let proto = parse_input(
+            r#"
+            package google.api;
+            import "google/protobuf/descriptor.proto";
+            // The http rule.
+            extend google.protobuf.MethodOptions {
+                HttpRule http = 72295728;
+            }
+            message HttpRule {
+                extend .google.protobuf.FieldOptions { repeated string tags = 50001; }
+                string get = 2;
+            }
+        "#,
+        );
+
+        assert_eq!(proto.messages.len(), 1);
+        assert_eq!(proto.messages[0].fields.len(), 1);
+
+        let http = &proto.extends[0];
+        assert_eq!(http.extendee, "google.protobuf.MethodOptions");
+        assert_eq!(http.full_name(&http.fields[0]), "google.api.http");
+        assert_eq!(http.fields[0].number, 72295728);
+        assert_eq!(http.comments.doc().as_deref(), Some("The http rule."));
+
+        let tags = &proto.messages[0].extends[0];
+        assert_eq!(tags.extendee, ".google.protobuf.FieldOptions");
+        assert_eq!(tags.full_name(&tags.fields[0]), "google.api.HttpRule.tags");
+        assert!(tags.fields[0].repeated);
+        assert_eq!(proto.all_extends().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_custom_method_options() {
+        let proto = parse_input(
+            r#"
+            package shop;
+            service Orders {
+                rpc Cancel (Order) returns (Order) {
+                    option deprecated = true;
+                    option (acme.auth.required) = true;
+                    option (acme.limits) = { rate: 10 burst { size: 20 } };
+                }
+            }
+        "#,
+        );
+
+        let cancel = &proto.services[0].methods["Cancel"];
+        assert_eq!(cancel.options["(acme.limits).burst.size"], "20");
+        let mut custom: Vec<_> = cancel.custom_options().collect();
+        custom.sort();
+        assert_eq!(
+            custom,
+            vec![
+                ("acme.auth.required", "", "true"),
+                ("acme.limits", "burst.size", "20"),
+                ("acme.limits", "rate", "10"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_qualified_type_references() {
+        let proto = parse_input(
+            r#"
+            package shop.v1;
+            service Shop {
+                rpc Get (common.v1.GetRequest) returns (.google.protobuf.Empty);
+                rpc Watch (stream .shop.v1.Order) returns (stream Order.Item);
+            }
+            message Order {
+                message Item { string sku = 1; }
+                .shop.v1.Order.Item first = 1;
+                repeated Order.Item items = 2;
+            }
+        "#,
+        );
+
+        let methods = &proto.services[0].methods;
+        assert_eq!(methods["Get"].request, "common.v1.GetRequest");
+        assert_eq!(methods["Get"].response, ".google.protobuf.Empty");
+        assert!(methods["Watch"].client_streaming);
+        assert_eq!(methods["Watch"].request, ".shop.v1.Order");
+        assert_eq!(methods["Watch"].response, "Order.Item");
+
+        let order = &proto.messages[0];
+        assert_eq!(order.fields[0].field_type, ".shop.v1.Order.Item");
+
+        let item = proto
+            .resolve("shop.v1", &order.fields[0].field_type)
+            .unwrap();
+        assert_eq!(item.full_name(), "shop.v1.Order.Item");
+        let item = proto.resolve("shop.v1.Order", "v1.Order.Item").unwrap();
+        assert_eq!(item.full_name(), "shop.v1.Order.Item");
+    }
+
+    #[test]
+    fn test_resolve_first_component_shadows_outer_scopes() {
+        let proto = parse_input(
+            r#"
+            package pkg;
+            message Outer {
+                message Inner { string a = 1; }
+                message Deep {
+                    message Outer { string b = 1; }
+                    Outer.Inner shadowed = 1;
+                }
+            }
+        "#,
+        );
+
+        // `Outer` resolves to `pkg.Outer.Deep.Outer` first, which has no
+        // `Inner`, so protoc reports the reference as undefined.
+        assert!(proto.resolve("pkg.Outer.Deep", "Outer.Inner").is_none());
+        assert!(
+            proto
+                .resolve("pkg.Outer.Deep", ".pkg.Outer.Inner")
+                .is_some()
+        );
+        assert!(proto.resolve("pkg", "Outer.Inner").is_some());
     }
 
     #[test]
@@ -414,4 +2252,60 @@ mod tests {
         assert_eq!(proto.messages.len(), 1);
         assert_eq!(proto.services.len(), 1);
     }
+
+    #[test]
+    fn test_attach_comments() {
+        let proto = parse_input(
+            r#"// Copyright Acme.
+
+syntax = "proto3";
+
+// Detached.
+
+/**
+ * A book.
+ */
+message Book {
+  // The title.
+  // Required.
+  string title = 1; // shown in lists
+  string isbn = 2;
+
+  /* Stock. */ int32 stock = 3;
+  oneof kind { // how it ships
+    string ebook = 4;
+  }
+} // end of Book
+
+service Books {
+  // Looks up a book.
+  rpc Get (Book) returns (Book) {} // cached
+}
+"#,
+        );
+
+        assert_eq!(proto.comments.leading, vec!["// Copyright Acme.", ""]);
+
+        let book = &proto.messages[0];
+        assert_eq!(
+            book.comments.leading,
+            vec!["", "// Detached.", "", "/**\n * A book.\n */"]
+        );
+        assert_eq!(book.comments.doc().as_deref(), Some("A book."));
+        assert_eq!(book.comments.trailing.as_deref(), Some("// end of Book"));
+
+        let title = &book.fields[0].comments;
+        assert_eq!(title.doc().as_deref(), Some("The title.\nRequired."));
+        assert_eq!(title.trailing.as_deref(), Some("// shown in lists"));
+        assert_eq!(book.fields[1].comments, Comments::default());
+        assert_eq!(book.fields[2].comments.leading, vec!["", "/* Stock. */"]);
+        assert_eq!(
+            book.oneofs[0].fields[0].comments.leading,
+            vec!["// how it ships"]
+        );
+
+        let get = &proto.services[0].methods["Get"];
+        assert_eq!(get.comments.doc().as_deref(), Some("Looks up a book."));
+        assert_eq!(get.comments.trailing.as_deref(), Some("// cached"));
+    }
 }
@@ -0,0 +1,722 @@
+use std::{collections::HashMap, fmt, ops::RangeInclusive, path::Path};
+
+use crate::{
+    error::Error,
+    lexer::Lexer,
+    model::{
+        Comments, Enum, Extend, Field, HttpRule, Label, MAX_FIELD_NUMBER, Message, Oneof, Proto,
+        Service,
+    },
+    parser::Parser,
+};
+
+/// Options declared as strings in descriptor.proto.
+const STRING_OPTIONS: &[&str] = &[
+    "java_package",
+    "java_outer_classname",
+    "go_package",
+    "csharp_namespace",
+    "objc_class_prefix",
+    "php_class_prefix",
+    "php_namespace",
+    "php_metadata_namespace",
+    "ruby_package",
+    "swift_prefix",
+    "json_name",
+];
+
+/// Renders `proto` as canonical `.proto` source: two-space indentation, one
+/// statement per line, options sorted by name and declarations kept in
+/// source order along with their comments.
+///
+/// Only what the model holds is printed. Aggregate option values are kept
+/// on RPCs only, where other options than `google.api.http` are written one
+/// field per statement, as in `option (acme.auth).required = true;`.
+/// Comments inside a statement or after the last declaration of a block are
+/// dropped. Use [`format`] to format a file without losing anything else.
+pub fn print(proto: &Proto) -> String {
+    let mut printer = Printer::default();
+    printer.comments(&proto.comments);
+
+    if let Some(syntax) = &proto.syntax {
+        printer.line(format!("syntax = \"{}\";", syntax));
+        printer.blank();
+    }
+    if let Some(edition) = &proto.edition {
+        printer.line(format!("edition = {};", quote(edition)));
+        printer.blank();
+    }
+    if let Some(package) = &proto.package {
+        printer.line(format!("package {};", package));
+        printer.blank();
+    }
+    for import in &proto.imports {
+        let modifier = if proto.public_imports.contains(import) {
+            "public "
+        } else if proto.weak_imports.contains(import) {
+            "weak "
+        } else {
+            ""
+        };
+        printer.line(format!("import {}{};", modifier, quote(import)));
+    }
+    printer.blank();
+    for (name, value) in sorted(&proto.options) {
+        printer.line(format!("option {} = {};", name, constant(name, value)));
+    }
+
+    let extend_fields = proto.extends.iter().flat_map(|e| &e.fields);
+    let groups = groups(&proto.messages, extend_fields);
+    let mut items: Vec<Item> = proto
+        .messages
+        .iter()
+        .filter(|m| !groups.iter().any(|g| std::ptr::eq(*g, *m)))
+        .map(Item::Message)
+        .chain(proto.enums.iter().map(Item::Enum))
+        .chain(proto.services.iter().map(Item::Service))
+        .chain(proto.extends.iter().map(Item::Extend))
+        .collect();
+    items.sort_by_key(Item::start);
+    for item in items {
+        printer.blank();
+        printer.item(item, &groups);
+    }
+
+    printer.finish()
+}
+
+/// Formats the proto file at `path` with [`print`], failing instead if that
+/// would change what the file means: see [`Parser::lossless`].
+pub fn format(path: &Path, content: &str) -> Result<String, Error> {
+    let proto = Lexer::new()
+        .lex(content)
+        .and_then(|tokens| Parser::new(&tokens).lossless().parse())
+        .map_err(|e| Error::File {
+            path: path.to_path_buf(),
+            content: content.to_string(),
+            error: Box::new(e),
+        })?;
+    Ok(print(&proto))
+}
+
+/// A declaration printed in source order among its siblings.
+enum Item<'a> {
+    Field(&'a Field),
+    Oneof(&'a Oneof),
+    Message(&'a Message),
+    Enum(&'a Enum),
+    Service(&'a Service),
+    Extend(&'a Extend),
+}
+
+impl Item<'_> {
+    fn start(&self) -> usize {
+        match self {
+            Item::Field(f) => f.span.start,
+            Item::Oneof(o) => o.span.start,
+            Item::Message(m) => m.span.start,
+            Item::Enum(e) => e.span.start,
+            Item::Service(s) => s.span.start,
+            Item::Extend(e) => e.span.start,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    lines: Vec<String>,
+    indent: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: impl AsRef<str>) {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.indent), text.as_ref()));
+    }
+
+    fn blank(&mut self) {
+        self.lines.push(String::new());
+    }
+
+    /// Prints leading comments; block comments are re-indented line by line.
+    fn comments(&mut self, comments: &Comments) {
+        for comment in &comments.leading {
+            if comment.is_empty() {
+                self.blank();
+                continue;
+            }
+            for (i, line) in comment.lines().enumerate() {
+                let line = line.trim();
+                match i {
+                    0 => self.line(line),
+                    _ if line.starts_with('*') => self.line(format!(" {}", line)),
+                    _ => self.line(line),
+                }
+            }
+        }
+    }
+
+    /// Prints `text` as a statement with its comments.
+    fn statement(&mut self, text: String, comments: &Comments) {
+        self.comments(comments);
+        self.line(with_trailing(text, comments));
+    }
+
+    /// Opens a block with its comments and indents what follows.
+    fn open(&mut self, text: String, comments: &Comments) {
+        self.comments(comments);
+        self.line(format!("{} {{", text));
+        self.indent += 1;
+    }
+
+    fn close(&mut self, comments: &Comments) {
+        self.indent -= 1;
+        self.line(with_trailing("}".to_string(), comments));
+    }
+
+    /// Prints a nested declaration. `groups` are the messages declared by
+    /// group fields of the enclosing message, printed inline with the field.
+    fn item(&mut self, item: Item, groups: &[&Message]) {
+        match item {
+            Item::Field(field) => self.field(field, groups),
+            Item::Oneof(oneof) => {
+                self.open(format!("oneof {}", oneof.name), &oneof.comments);
+                for field in &oneof.fields {
+                    self.field(field, groups);
+                }
+                self.close(&oneof.comments);
+            }
+            Item::Message(message) => {
+                self.open(format!("message {}", message.name), &message.comments);
+                self.message_body(message);
+                self.close(&message.comments);
+            }
+            Item::Enum(e) => self.enumeration(e),
+            Item::Service(service) => self.service(service),
+            Item::Extend(extend) => {
+                self.open(format!("extend {}", extend.extendee), &extend.comments);
+                for field in &extend.fields {
+                    self.field(field, groups);
+                }
+                self.close(&extend.comments);
+            }
+        }
+    }
+
+    fn message_body(&mut self, message: &Message) {
+        for (name, value) in sorted(&message.options) {
+            self.line(format!("option {} = {};", name, constant(name, value)));
+        }
+        self.reserved(
+            &message.reserved_numbers,
+            MAX_FIELD_NUMBER,
+            &message.reserved_names,
+        );
+        if !message.extensions.is_empty() {
+            let extensions = ranges(&message.extensions, MAX_FIELD_NUMBER);
+            self.line(format!("extensions {};", extensions));
+        }
+        self.blank();
+
+        let fields = message
+            .fields
+            .iter()
+            .chain(message.oneofs.iter().flat_map(|o| &o.fields))
+            .chain(message.extends.iter().flat_map(|e| &e.fields));
+        let groups = groups(&message.messages, fields);
+
+        let mut items: Vec<Item> = message
+            .fields
+            .iter()
+            .map(Item::Field)
+            .chain(message.oneofs.iter().map(Item::Oneof))
+            .chain(
+                message
+                    .messages
+                    .iter()
+                    .filter(|m| !groups.iter().any(|g| std::ptr::eq(*g, *m)))
+                    .map(Item::Message),
+            )
+            .chain(message.enums.iter().map(Item::Enum))
+            .chain(message.extends.iter().map(Item::Extend))
+            .collect();
+        items.sort_by_key(Item::start);
+        for item in items {
+            let block = !matches!(item, Item::Field(_));
+            if block {
+                self.blank();
+            }
+            self.item(item, &groups);
+            if block {
+                self.blank();
+            }
+        }
+    }
+
+    fn field(&mut self, field: &Field, groups: &[&Message]) {
+        let label = match (field.repeated, field.label) {
+            (true, _) => "repeated ",
+            (false, Some(Label::Optional)) => "optional ",
+            (false, Some(Label::Required)) => "required ",
+            (false, None) => "",
+        };
+        let options = field_options(field);
+
+        if field.group
+            && let Some(group) = groups.iter().find(|g| g.name == field.field_type)
+        {
+            self.open(
+                format!(
+                    "{}group {} = {}{}",
+                    label, field.field_type, field.number, options
+                ),
+                &field.comments,
+            );
+            self.message_body(group);
+            self.close(&field.comments);
+            return;
+        }
+
+        let field_type = match &field.key_type {
+            Some(key) => format!("map<{}, {}>", key, field.field_type),
+            None => field.field_type.clone(),
+        };
+        self.statement(
+            format!(
+                "{}{} {} = {}{};",
+                label, field_type, field.name, field.number, options
+            ),
+            &field.comments,
+        );
+    }
+
+    fn enumeration(&mut self, e: &Enum) {
+        self.open(format!("enum {}", e.name), &e.comments);
+        if e.allow_alias {
+            self.line("option allow_alias = true;");
+        }
+        for (name, value) in sorted(&e.options) {
+            self.line(format!("option {} = {};", name, constant(name, value)));
+        }
+        self.reserved(&e.reserved_numbers, i32::MAX, &e.reserved_names);
+        for value in &e.values {
+            self.statement(
+                format!(
+                    "{} = {}{};",
+                    value.name,
+                    value.number,
+                    bracket_options(&value.options, constant)
+                ),
+                &value.comments,
+            );
+        }
+        self.close(&e.comments);
+    }
+
+    fn reserved<T>(&mut self, numbers: &[RangeInclusive<T>], max: T, names: &[String])
+    where
+        T: Copy + PartialEq + fmt::Display,
+    {
+        if !numbers.is_empty() {
+            self.line(format!("reserved {};", ranges(numbers, max)));
+        }
+        if !names.is_empty() {
+            let names: Vec<String> = names.iter().map(|n| quote(n)).collect();
+            self.line(format!("reserved {};", names.join(", ")));
+        }
+    }
+
+    fn service(&mut self, service: &Service) {
+        self.open(format!("service {}", service.name), &service.comments);
+
+        let mut methods: Vec<_> = service.methods.values().collect();
+        methods.sort_by(|a, b| (a.span.start, &a.name).cmp(&(b.span.start, &b.name)));
+        for method in methods {
+            let stream = |streaming| if streaming { "stream " } else { "" };
+            let signature = format!(
+                "rpc {}({}{}) returns ({}{})",
+                method.name,
+                stream(method.client_streaming),
+                method.request,
+                stream(method.server_streaming),
+                method.response
+            );
+            if method.options.is_empty() && method.http.is_none() {
+                self.statement(format!("{};", signature), &method.comments);
+                continue;
+            }
+
+            self.open(signature, &method.comments);
+            if let Some(http) = &method.http {
+                self.line("option (google.api.http) = {");
+                self.indent += 1;
+                self.http_rule(http);
+                self.indent -= 1;
+                self.line("};");
+            }
+            for (name, value) in sorted(&method.options) {
+                self.line(format!("option {} = {};", name, constant(name, value)));
+            }
+            self.close(&method.comments);
+        }
+
+        self.close(&service.comments);
+    }
+
+    fn http_rule(&mut self, rule: &HttpRule) {
+        let method = rule.method.to_lowercase();
+        match method.as_str() {
+            "get" | "put" | "post" | "delete" | "patch" => {
+                self.line(format!("{}: {}", method, quote(&rule.path)));
+            }
+            _ => {
+                self.line("custom {");
+                self.line(format!("  kind: {}", quote(&rule.method)));
+                self.line(format!("  path: {}", quote(&rule.path)));
+                self.line("}");
+            }
+        }
+        if let Some(body) = &rule.body {
+            self.line(format!("body: {}", quote(body)));
+        }
+        for binding in &rule.additional_bindings {
+            self.line("additional_bindings {");
+            self.indent += 1;
+            self.http_rule(binding);
+            self.indent -= 1;
+            self.line("}");
+        }
+    }
+
+    /// Joins the lines, dropping repeated blank lines and blank lines at the
+    /// start or end of a block.
+    fn finish(self) -> String {
+        let mut out: Vec<String> = Vec::new();
+        for line in self.lines {
+            let last = out.last().map(|l| l.trim_end());
+            if line.is_empty() && (last.is_none_or(|l| l.is_empty() || l.ends_with('{'))) {
+                continue;
+            }
+            if line.trim_start().starts_with('}') && out.last().is_some_and(|l| l.is_empty()) {
+                out.pop();
+            }
+            out.push(line);
+        }
+        while out.last().is_some_and(|l| l.is_empty()) {
+            out.pop();
+        }
+        out.join("\n") + "\n"
+    }
+}
+
+/// The messages among `messages` declared by group fields in `fields`.
+fn groups<'a>(
+    messages: &'a [Message],
+    fields: impl Iterator<Item = &'a Field>,
+) -> Vec<&'a Message> {
+    fields
+        .filter(|f| f.group)
+        .filter_map(|f| messages.iter().find(|m| m.name == f.field_type))
+        .collect()
+}
+
+fn with_trailing(text: String, comments: &Comments) -> String {
+    match &comments.trailing {
+        Some(trailing) => format!("{} {}", text, trailing),
+        None => text,
+    }
+}
+
+fn sorted(options: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut options: Vec<_> = options.iter().collect();
+    options.sort();
+    options
+}
+
+fn field_options(field: &Field) -> String {
+    bracket_options(&field.options, |name, value| {
+        match (name, field.field_type.as_str()) {
+            // Defaults are written the way the field's type is.
+            ("default", "string" | "bytes") => quote(value),
+            ("default", _) => value.to_string(),
+            _ => constant(name, value),
+        }
+    })
+}
+
+fn bracket_options(
+    options: &HashMap<String, String>,
+    constant: impl Fn(&str, &str) -> String,
+) -> String {
+    if options.is_empty() {
+        return String::new();
+    }
+    let options: Vec<String> = sorted(options)
+        .into_iter()
+        .map(|(name, value)| format!("{} = {}", name, constant(name, value)))
+        .collect();
+    format!(" [{}]", options.join(", "))
+}
+
+/// Writes ranges as in `reserved 2, 9 to 11, 40 to max`, where `max` is the
+/// largest field or enum number.
+fn ranges<T: Copy + PartialEq + fmt::Display>(ranges: &[RangeInclusive<T>], max: T) -> String {
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|r| match (*r.start(), *r.end()) {
+            (start, end) if start == end => start.to_string(),
+            (start, end) if end == max => format!("{} to max", start),
+            (start, end) => format!("{} to {}", start, end),
+        })
+        .collect();
+    ranges.join(", ")
+}
+
+/// Writes an option value as a constant. The model keeps values without
+/// their quotes, so besides the string options of descriptor.proto, values
+/// are quoted unless they are numbers, booleans or enum values in
+/// `UPPER_SNAKE_CASE`.
+fn constant(name: &str, value: &str) -> String {
+    if is_bare(name, value) {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+/// Whether the value of option `name` is printed without quotes, as a
+/// number, bool or enum value.
+pub(crate) fn is_bare(name: &str, value: &str) -> bool {
+    !STRING_OPTIONS.contains(&name)
+        && (value.parse::<f64>().is_ok()
+            || value.starts_with("0x")
+            || matches!(value, "true" | "false")
+            || is_enum_value(value))
+}
+
+/// Quotes a string, escaping what can't appear in a literal as written.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_enum_value(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_uppercase())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn parse(content: &str) -> Proto {
+        let tokens = Lexer::new().lex(content).unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_print_canonical_proto() {
+        let proto = parse(
+            r#"// Shop API.
+syntax = "proto3";
+package shop.v1;
+import "google/api/annotations.proto";
+option java_package = "com.shop";   option optimize_for = SPEED;
+
+/* Order states. */
+enum Status { STATUS_UNSPECIFIED = 0; STATUS_OPEN = 1 [deprecated = true]; }
+
+// An order.
+message Order {
+  reserved 5, 9 to 11, 100 to max; reserved "legacy";
+  string id = 1; // unique
+  map<string, int64> labels = 2 [json_name = "tags"];
+
+  optional Status status = 3;
+  oneof payment { string card = 4; }
+  message Line { int32 qty = 1; }
+}
+
+service Orders {
+  // Fetches an order.
+  rpc Get (Order) returns (stream Order) {
+    option (google.api.http) = { get: "/v1/orders/{id}" additional_bindings { post: "/v1/orders:get" body: "*" } };
+  }
+  rpc Watch (stream Order) returns (Order) { option (shop.v1.limits) = { rate: 5 }; }
+}
+
+// Custom options.
+extend google.protobuf.MethodOptions { bool required = 50001; }
+"#,
+        );
+
+        assert_eq!(
+            print(&proto),
+            r#"// Shop API.
+syntax = "proto3";
+
+package shop.v1;
+
+import "google/api/annotations.proto";
+
+option java_package = "com.shop";
+option optimize_for = SPEED;
+
+/* Order states. */
+enum Status {
+  STATUS_UNSPECIFIED = 0;
+  STATUS_OPEN = 1 [deprecated = true];
+}
+
+// An order.
+message Order {
+  reserved 5, 9 to 11, 100 to max;
+  reserved "legacy";
+
+  string id = 1; // unique
+  map<string, int64> labels = 2 [json_name = "tags"];
+
+  optional Status status = 3;
+
+  oneof payment {
+    string card = 4;
+  }
+
+  message Line {
+    int32 qty = 1;
+  }
+}
+
+service Orders {
+  // Fetches an order.
+  rpc Get(Order) returns (stream Order) {
+    option (google.api.http) = {
+      get: "/v1/orders/{id}"
+      additional_bindings {
+        post: "/v1/orders:get"
+        body: "*"
+      }
+    };
+  }
+  rpc Watch(stream Order) returns (Order) {
+    option (shop.v1.limits).rate = 5;
+  }
+}
+
+// Custom options.
+extend google.protobuf.MethodOptions {
+  bool required = 50001;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_print_proto2_groups_and_defaults() {
+        let source = r#"syntax = "proto2";
+
+message Search {
+  required string query = 1 [default = "*"];
+  optional Mode mode = 2 [default = FAST];
+
+  repeated group Result = 3 {
+    optional string url = 1;
+  }
+
+  enum Mode {
+    option allow_alias = true;
+    FAST = 0;
+    QUICK = 0;
+  }
+}
+"#;
+        let printed = print(&parse(source));
+        assert_eq!(printed, source);
+        // Printing is stable.
+        assert_eq!(print(&parse(&printed)), printed);
+    }
+
+    #[test]
+    fn test_print_enum_reserved() {
+        let source = r#"syntax = "proto3";
+
+enum Status {
+  reserved 2, 15 to 20, -5 to -1, 100 to max;
+  reserved "FOO";
+  STATUS_UNSPECIFIED = 0;
+}
+"#;
+        assert_eq!(print(&parse(source)), source);
+    }
+
+    #[test]
+    fn test_format_import_modifiers_and_literals() {
+        let source = r#"syntax = "proto3";
+
+import public "shop/v1/common.proto";
+import weak "shop/v1/legacy.proto";
+import "google/api/annotations.proto";
+
+option go_package = "123";
+option java_multiple_files = true;
+
+service Orders {
+  rpc Get(Order) returns (Order) {
+    option (shop.v1.limits).rate = 5;
+    option (shop.v1.mode) = FAST;
+  }
+}
+"#;
+        assert_eq!(format(Path::new("shop.proto"), source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_format_rejects_lossy_proto() {
+        let lossy = [
+            ("option (acme.file) = { enabled: true };", "aggregate value"),
+            (
+                "message A { string id = 1 [(validate.rules).string = { min_len: 1 }]; }",
+                "aggregate value",
+            ),
+            (
+                "message A { extensions 100 to 199 [verification = UNVERIFIED]; }",
+                "extension range",
+            ),
+            ("option (acme.code) = \"123\";", "without quotes"),
+            ("message A { option (acme.speed) = fast; }", "as a string"),
+            (
+                "service S { rpc M(A) returns (A) { option (acme.tags) = { tag: \"a\" tag: \"b\" }; } }",
+                "last value",
+            ),
+            (
+                "service S { rpc M(A) returns (A) { option (google.api.http) = { get: \"/a\" response_body: \"b\" }; } }",
+                "response_body",
+            ),
+        ];
+        for (statement, message) in lossy {
+            let source = format!("syntax = \"proto3\";\n{}\n", statement);
+            let Err(Error::File { error, .. }) = format(Path::new("a.proto"), &source) else {
+                panic!("formatted lossy proto: {}", statement);
+            };
+            let error = error.to_string();
+            assert!(error.contains("Formatting would"), "{}", error);
+            assert!(error.contains(message), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    lexer::Lexer,
+    model::{self, Definition, Extend, Field, Message, Proto, RpcMethod},
+    parser::Parser,
+    well_known,
+};
+
+/// Loads a proto file together with everything it imports, searching the
+/// include paths in order the way `protoc -I` does. Well-known types that are
+/// not found on disk fall back to the definitions embedded in this crate.
+pub struct Resolver {
+    include_paths: Vec<PathBuf>,
+}
+
+/// A parsed proto file and the name it was imported as.
+#[derive(Debug)]
+pub struct ProtoFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub content: String,
+    pub proto: Proto,
+}
+
+/// Every file reachable from a root proto, acting as a symbol table across
+/// files and packages. The root file comes first.
+#[derive(Debug)]
+pub struct ProtoSet {
+    pub files: Vec<ProtoFile>,
+}
+
+impl ProtoFile {
+    /// Parses `content` on its own, without loading its imports.
+    pub fn parse(name: String, path: PathBuf, content: &str) -> Result<ProtoFile, Error> {
+        let (file, error) = ProtoFile::parse_partial(name, path, content);
+        match error {
+            Some(error) => Err(error),
+            None => Ok(file),
+        }
+    }
+
+    /// Parses what it can of `content`, along with its syntax errors as one
+    /// [`Error::File`].
+    fn parse_partial(name: String, path: PathBuf, content: &str) -> (ProtoFile, Option<Error>) {
+        let (proto, mut errors) = match Lexer::new().lex(content) {
+            Ok(tokens) => Parser::new(&tokens).parse_partial(),
+            Err(e) => (Proto::default(), vec![e]),
+        };
+        let error = match errors.len() {
+            0 => None,
+            1 => Some(errors.remove(0)),
+            _ => Some(Error::Multiple(errors)),
+        }
+        .map(|e| Error::File {
+            path: path.clone(),
+            content: content.to_string(),
+            error: Box::new(e),
+        });
+
+        let file = ProtoFile {
+            name,
+            path,
+            content: content.to_string(),
+            proto,
+        };
+        (file, error)
+    }
+}
+
+impl Resolver {
+    pub fn new(include_paths: Vec<PathBuf>) -> Self {
+        Resolver { include_paths }
+    }
+
+    /// Loads `root` and its imports. Every file is loaded even when some fail
+    /// to parse, so that all of their errors are reported together.
+    pub fn resolve(&self, root: &Path) -> Result<ProtoSet, Error> {
+        let name = root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        self.load(name, root.to_path_buf(), &mut files, &mut errors);
+
+        match errors.len() {
+            0 => Ok(ProtoSet { files }),
+            1 => Err(errors.remove(0)),
+            _ => Err(Error::Multiple(errors)),
+        }
+    }
+
+    fn load(
+        &self,
+        name: String,
+        path: PathBuf,
+        files: &mut Vec<ProtoFile>,
+        errors: &mut Vec<Error>,
+    ) {
+        match fs::read_to_string(&path) {
+            Ok(content) => self.load_source(name, path, &content, files, errors),
+            Err(e) => errors.push(Error::Io {
+                path,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    fn load_source(
+        &self,
+        name: String,
+        path: PathBuf,
+        content: &str,
+        files: &mut Vec<ProtoFile>,
+        errors: &mut Vec<Error>,
+    ) {
+        let (file, error) = ProtoFile::parse_partial(name, path, content);
+        errors.extend(error);
+        let imports = file.proto.imports.clone();
+        files.push(file);
+
+        for import in imports {
+            if files.iter().any(|f| f.name == import) {
+                continue;
+            }
+            if let Some(path) = self.find(&import) {
+                self.load(import, path, files, errors);
+            } else if let Some(source) = well_known::source(&import) {
+                let path = PathBuf::from(&import);
+                self.load_source(import, path, source, files, errors);
+            } else if !import.starts_with("google/protobuf/") {
+                // Other google/protobuf files (descriptor.proto, ...) only
+                // carry option definitions and ship with protoc itself.
+                errors.push(Error::Import {
+                    name: import,
+                    include_paths: self.include_paths.clone(),
+                });
+            }
+        }
+    }
+
+    fn find(&self, import: &str) -> Option<PathBuf> {
+        self.include_paths
+            .iter()
+            .map(|dir| dir.join(import))
+            .find(|path| path.is_file())
+    }
+}
+
+impl ProtoSet {
+    pub fn root(&self) -> &Proto {
+        &self.files[0].proto
+    }
+
+    /// Makes the file named `name` the root. Returns false if it is not loaded.
+    pub fn set_root(&mut self, name: &str) -> bool {
+        match self.files.iter().position(|f| f.name == name) {
+            Some(index) => {
+                let file = self.files.remove(index);
+                self.files.insert(0, file);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Looks up a message or enum by its fully-qualified name in any file.
+    pub fn find(&self, full_name: &str) -> Option<Definition<'_>> {
+        self.files.iter().find_map(|f| f.proto.find(full_name))
+    }
+
+    /// Returns the file declaring the fully-qualified `full_name`.
+    pub fn file_of(&self, full_name: &str) -> Option<&Proto> {
+        self.files
+            .iter()
+            .map(|f| &f.proto)
+            .find(|p| p.find(full_name).is_some())
+    }
+
+    /// Resolves a type reference across all files, see [`Proto::resolve`].
+    pub fn resolve(&self, scope: &str, name: &str) -> Option<Definition<'_>> {
+        model::resolve_in_scope(
+            scope,
+            name,
+            |candidate| self.find(candidate),
+            |candidate| self.files.iter().any(|f| f.proto.in_package(candidate)),
+        )
+    }
+
+    pub fn resolve_message(&self, scope: &str, name: &str) -> Option<&Message> {
+        match self.resolve(scope, name) {
+            Some(Definition::Message(m)) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Looks up the extension a custom option names, `acme.auth.required` for
+    /// `(acme.auth.required)`, trying `scope` and then each parent scope.
+    pub fn resolve_extension(&self, scope: &str, name: &str) -> Option<(&Extend, &Field)> {
+        let extends: Vec<&Extend> = self
+            .files
+            .iter()
+            .flat_map(|f| f.proto.all_extends())
+            .collect();
+        let find = |full_name: &str| {
+            extends.iter().find_map(|extend| {
+                let field = extend
+                    .fields
+                    .iter()
+                    .find(|f| extend.full_name(f) == full_name)?;
+                Some((*extend, field))
+            })
+        };
+        if let Some(full_name) = name.strip_prefix('.') {
+            return find(full_name);
+        }
+
+        let mut scope = scope;
+        loop {
+            let candidate = match scope {
+                "" => name.to_string(),
+                scope => format!("{}.{}", scope, name),
+            };
+            if let Some(found) = find(&candidate) {
+                return Some(found);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rfind('.').map_or("", |i| &scope[..i]);
+        }
+    }
+
+    /// The custom options of `method`, declared in the service with the
+    /// fully-qualified name `service`, keyed by fully-qualified name. The path
+    /// to a field of an aggregate value is appended, so both
+    /// `(acme.auth.required)` and `(acme.auth).required` can give
+    /// `acme.auth.required`. Options whose extension is not loaded keep the
+    /// name as written.
+    pub fn custom_options<'a>(
+        &self,
+        service: &str,
+        method: &'a RpcMethod,
+    ) -> HashMap<String, &'a str> {
+        method
+            .custom_options()
+            .map(|(extension, path, value)| {
+                let name = match self.resolve_extension(service, extension) {
+                    Some((extend, field)) => extend.full_name(field),
+                    None => extension.trim_start_matches('.').to_string(),
+                };
+                match path {
+                    "" => (name, value),
+                    path => (format!("{}.{}", name, path), value),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_imports_across_packages() {
+        let dir = std::env::temp_dir().join(format!("proto_def_resolver_{}", std::process::id()));
+        let includes = dir.join("includes");
+        write(
+            &dir,
+            "books.proto",
+            r#"
+            syntax = "proto3";
+            package books;
+            import "common/money.proto";
+            import "google/protobuf/empty.proto";
+            message Book { common.Money price = 1; }
+        "#,
+        );
+        write(
+            &includes,
+            "common/money.proto",
+            r#"
+            syntax = "proto3";
+            package common;
+            message Money { int64 units = 1; }
+        "#,
+        );
+
+        let resolver = Resolver::new(vec![includes.clone(), dir.clone()]);
+        let set = resolver.resolve(&dir.join("books.proto")).unwrap();
+
+        assert_eq!(set.files.len(), 3);
+        assert_eq!(set.files[1].name, "common/money.proto");
+        assert_eq!(set.files[2].name, "google/protobuf/empty.proto");
+        assert!(set.find("google.protobuf.Empty").is_some());
+        assert_eq!(set.root().package.as_deref(), Some("books"));
+
+        let money = set.resolve_message("books.Book", "common.Money").unwrap();
+        assert_eq!(money.full_name, "common.Money");
+        assert_eq!(
+            set.file_of("common.Money").unwrap().package.as_deref(),
+            Some("common")
+        );
+
+        let missing = Resolver::new(vec![dir.clone()]).resolve(&dir.join("books.proto"));
+        assert!(matches!(
+            missing.unwrap_err(),
+            Error::Import { name, .. } if name == "common/money.proto"
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_errors_in_every_file() {
+        let dir = std::env::temp_dir().join(format!("proto_def_errors_{}", std::process::id()));
+        write(
+            &dir,
+            "books.proto",
+            r#"
+            syntax = "proto3";
+            import "money.proto";
+            import "missing.proto";
+            message Book { string title = ; int32 pages = x; }
+        "#,
+        );
+        write(&dir, "money.proto", "message Money { int64 units = 1 }");
+
+        let error = Resolver::new(vec![dir.clone()])
+            .resolve(&dir.join("books.proto"))
+            .unwrap_err();
+        let Error::Multiple(errors) = &error else {
+            panic!("expected several errors, got {:?}", error);
+        };
+        let files: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                Error::File { path, error, .. } => {
+                    let count = match error.as_ref() {
+                        Error::Multiple(errors) => errors.len(),
+                        _ => 1,
+                    };
+                    (path.file_name().unwrap().to_str().unwrap(), count)
+                }
+                Error::Import { name, .. } => (name.as_str(), 0),
+                other => panic!("unexpected error {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            files,
+            [("books.proto", 2), ("money.proto", 1), ("missing.proto", 0)]
+        );
+        assert_eq!(error.to_string().matches("error: ").count(), 3);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_custom_options() {
+        let parse = |name: &str, content: &str| {
+            ProtoFile::parse(name.into(), PathBuf::from(name), content).unwrap()
+        };
+        let set = ProtoSet {
+            files: vec![
+                parse(
+                    "acme/orders.proto",
+                    r#"
+                    syntax = "proto3";
+                    package acme.orders;
+                    import "acme/auth.proto";
+                    message Order {}
+                    service Orders {
+                        rpc Cancel (Order) returns (Order) {
+                            option (auth.required) = true;
+                            option (.acme.auth.policy) = { role: "admin" };
+                            option (other.flag) = 1;
+                        }
+                    }
+                "#,
+                ),
+                parse(
+                    "acme/auth.proto",
+                    r#"
+                    syntax = "proto3";
+                    package acme.auth;
+                    import "google/protobuf/descriptor.proto";
+                    message Policy { string role = 1; }
+                    extend google.protobuf.MethodOptions {
+                        bool required = 50001;
+                        Policy policy = 50002;
+                    }
+                "#,
+                ),
+            ],
+        };
+
+        let (extend, field) = set
+            .resolve_extension("acme.orders.Orders", "auth.required")
+            .unwrap();
+        assert_eq!(extend.full_name(field), "acme.auth.required");
+        assert!(set.resolve_extension("acme.orders", "required").is_none());
+
+        let cancel = &set.root().services[0].methods["Cancel"];
+        let options = set.custom_options("acme.orders.Orders", cancel);
+        assert_eq!(options.len(), 3);
+        assert_eq!(options["acme.auth.required"], "true");
+        assert_eq!(options["acme.auth.policy.role"], "admin");
+        assert_eq!(options["other.flag"], "1");
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Span},
+    model::{self, Definition, Field, MAX_FIELD_NUMBER, Message, Proto, Service},
+    resolver::{ProtoFile, ProtoSet},
+    well_known,
+};
+
+/// Field numbers set aside for the protobuf implementation.
+const IMPLEMENTATION_RESERVED: std::ops::RangeInclusive<u32> = 19_000..=19_999;
+
+/// Checks what the parser cannot see on its own: field numbers and names
+/// within each message, and that every referenced type is defined in one of
+/// the loaded files. All problems are reported, not just the first.
+pub fn validate(protos: &ProtoSet) -> Result<(), Error> {
+    let mut errors = Vec::new();
+    for file in &protos.files {
+        let mut file_errors = Vec::new();
+        check_file(protos, &file.proto, &mut file_errors);
+        errors.extend(file_errors.into_iter().map(|error| in_file(file, error)));
+    }
+
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(Error::Multiple(errors)),
+    }
+}
+
+fn in_file(file: &ProtoFile, error: Error) -> Error {
+    Error::File {
+        path: file.path.clone(),
+        content: file.content.clone(),
+        error: Box::new(error),
+    }
+}
+
+fn error(span: Span, message: String) -> Error {
+    Error::Parse { span, message }
+}
+
+fn check_file(protos: &ProtoSet, proto: &Proto, errors: &mut Vec<Error>) {
+    for message in &proto.messages {
+        check_message(protos, message, errors);
+    }
+    for service in &proto.services {
+        check_service(protos, proto, service, errors);
+    }
+    for extend in proto.all_extends() {
+        for field in &extend.fields {
+            check_type(protos, &extend.scope, field, errors);
+        }
+    }
+}
+
+fn check_message(protos: &ProtoSet, message: &Message, errors: &mut Vec<Error>) {
+    let fields = message
+        .fields
+        .iter()
+        .chain(message.oneofs.iter().flat_map(|o| &o.fields));
+
+    let mut numbers: HashMap<u32, &str> = HashMap::new();
+    let mut names: HashMap<&str, u32> = HashMap::new();
+    for field in fields {
+        check_number(message, field, errors);
+
+        if let Some(first) = numbers.insert(field.number, &field.name) {
+            errors.push(error(
+                field.span,
+                format!(
+                    "Field number {} in '{}' is already used by '{}'",
+                    field.number, message.full_name, first
+                ),
+            ));
+        }
+        if names.insert(&field.name, field.number).is_some() {
+            errors.push(error(
+                field.span,
+                format!(
+                    "Field '{}' is already defined in '{}'",
+                    field.name, message.full_name
+                ),
+            ));
+        }
+        if message.reserved_names.contains(&field.name) {
+            errors.push(error(
+                field.span,
+                format!(
+                    "Field name '{}' is reserved in '{}'",
+                    field.name, message.full_name
+                ),
+            ));
+        }
+
+        check_type(protos, &message.full_name, field, errors);
+    }
+
+    for nested in &message.messages {
+        check_message(protos, nested, errors);
+    }
+}
+
+/// Types from protoc's own `google/protobuf` files (descriptor.proto, ...)
+/// are not loaded unless imported, so known names among them are accepted.
+fn check_type(protos: &ProtoSet, scope: &str, field: &Field, errors: &mut Vec<Error>) {
+    if !model::is_scalar(&field.field_type)
+        && protos.resolve(scope, &field.field_type).is_none()
+        && !well_known::is_known_type(&field.field_type)
+    {
+        errors.push(error(
+            field.span,
+            format!("Undefined type '{}'", field.field_type),
+        ));
+    }
+}
+
+fn check_number(message: &Message, field: &Field, errors: &mut Vec<Error>) {
+    let number = field.number;
+    let problem = if number == 0 || number > MAX_FIELD_NUMBER {
+        format!(
+            "Field number {} is out of range 1 to {}",
+            number, MAX_FIELD_NUMBER
+        )
+    } else if IMPLEMENTATION_RESERVED.contains(&number) {
+        format!(
+            "Field number {} is reserved for the protobuf implementation (19000 to 19999)",
+            number
+        )
+    } else if message.reserved_numbers.iter().any(|r| r.contains(&number)) {
+        format!(
+            "Field number {} is reserved in '{}'",
+            number, message.full_name
+        )
+    } else {
+        return;
+    };
+    errors.push(error(field.span, problem));
+}
+
+fn check_service(protos: &ProtoSet, proto: &Proto, service: &Service, errors: &mut Vec<Error>) {
+    let scope = match &proto.package {
+        Some(package) => format!("{}.{}", package, service.name),
+        None => service.name.clone(),
+    };
+
+    let mut methods: Vec<_> = service.methods.values().collect();
+    methods.sort_by_key(|m| m.span.start);
+    for method in methods {
+        for name in [&method.request, &method.response] {
+            match protos.resolve(&scope, name) {
+                Some(Definition::Message(_)) => {}
+                Some(Definition::Enum(_)) => errors.push(error(
+                    method.span,
+                    format!("'{}' is an enum, RPCs take and return messages", name),
+                )),
+                None if well_known::is_known_type(name) => {}
+                None => errors.push(error(method.span, format!("Undefined type '{}'", name))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn protos(content: &str) -> ProtoSet {
+        let tokens = Lexer::new().lex(content).unwrap();
+        ProtoSet {
+            files: vec![ProtoFile {
+                name: "shop.proto".into(),
+                path: PathBuf::from("shop.proto"),
+                content: content.into(),
+                proto: Parser::new(&tokens).parse().unwrap(),
+            }],
+        }
+    }
+
+    fn messages(error: Error) -> Vec<String> {
+        match error {
+            Error::Multiple(errors) => errors.iter().map(Error::message).collect(),
+            error => vec![error.message()],
+        }
+    }
+
+    #[test]
+    fn test_valid_proto() {
+        let protos = protos(
+            r#"
+            syntax = "proto3";
+            package shop;
+            service Orders { rpc Get (Order) returns (Order); }
+            message Order {
+                reserved 4;
+                string id = 1;
+                Item item = 2;
+                oneof payment { string card = 3; }
+                message Item { shop.Order.Kind kind = 1; }
+                enum Kind { KIND_UNSPECIFIED = 0; }
+            }
+        "#,
+        );
+        assert_eq!(validate(&protos), Ok(()));
+    }
+
+    #[test]
+    fn test_report_all_problems() {
+        let protos = protos(
+            r#"
+            syntax = "proto3";
+            package shop;
+            service Orders {
+                rpc Get (Order) returns (Missing);
+                rpc List (Status) returns (Order);
+            }
+            enum Status { STATUS_UNSPECIFIED = 0; }
+            message Order {
+                reserved 7;
+                reserved "legacy";
+                string id = 1;
+                string name = 1;
+                string id = 2;
+                int32 internal = 19000;
+                int32 old = 7;
+                string legacy = 8;
+                Customer customer = 9;
+                oneof kind { string gift = 2; }
+                google.protobuf.FileDescriptorProto descriptor = 10;
+                google.protobuf.Timestmp created = 11;
+            }
+            extend google.protobuf.MethodOptions { Policy policy = 50001; }
+        "#,
+        );
+
+        assert_eq!(
+            messages(validate(&protos).unwrap_err()),
+            vec![
+                "Field number 1 in 'shop.Order' is already used by 'id'",
+                "Field 'id' is already defined in 'shop.Order'",
+                "Field number 19000 is reserved for the protobuf implementation (19000 to 19999)",
+                "Field number 7 is reserved in 'shop.Order'",
+                "Field name 'legacy' is reserved in 'shop.Order'",
+                "Undefined type 'Customer'",
+                "Undefined type 'google.protobuf.Timestmp'",
+                "Field number 2 in 'shop.Order' is already used by 'id'",
+                "Undefined type 'Missing'",
+                "'Status' is an enum, RPCs take and return messages",
+                "Undefined type 'Policy'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_render_with_their_file() {
+        let protos = protos("message M {\n  Missing m = 1;\n}\n");
+        assert_eq!(
+            validate(&protos).unwrap_err().to_string(),
+            "error: Undefined type 'Missing'\n  --> shop.proto:2:3\n  |\n2 |   Missing m = 1;\n  |   ^^^^^^^^^^^^^^"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Embedded definitions of the `google.protobuf` well-known types, so they
+//! resolve without the protoc include directory on the include path.
+
+const FILES: &[(&str, &str)] = &[
+    (
+        "google/protobuf/any.proto",
+        include_str!("well_known/any.proto"),
+    ),
+    (
+        "google/protobuf/duration.proto",
+        include_str!("well_known/duration.proto"),
+    ),
+    (
+        "google/protobuf/empty.proto",
+        include_str!("well_known/empty.proto"),
+    ),
+    (
+        "google/protobuf/field_mask.proto",
+        include_str!("well_known/field_mask.proto"),
+    ),
+    (
+        "google/protobuf/struct.proto",
+        include_str!("well_known/struct.proto"),
+    ),
+    (
+        "google/protobuf/timestamp.proto",
+        include_str!("well_known/timestamp.proto"),
+    ),
+    (
+        "google/protobuf/wrappers.proto",
+        include_str!("well_known/wrappers.proto"),
+    ),
+];
+
+/// Returns the source of a well-known type file by its import name, e.g.
+/// `google/protobuf/timestamp.proto`.
+pub fn source(import: &str) -> Option<&'static str> {
+    FILES
+        .iter()
+        .find(|(name, _)| *name == import)
+        .map(|(_, source)| *source)
+}
+
+/// Whether the fully-qualified `full_name` belongs to the `google.protobuf`
+/// package.
+pub fn is_well_known(full_name: &str) -> bool {
+    full_name
+        .trim_start_matches('.')
+        .starts_with("google.protobuf.")
+}
+
+/// Top-level messages and enums of the `google.protobuf` files, embedded or
+/// not (`descriptor.proto`, `api.proto`, ...).
+const TYPES: &[&str] = &[
+    // any.proto, duration.proto, empty.proto, field_mask.proto
+    "Any",
+    "Duration",
+    "Empty",
+    "FieldMask",
+    // struct.proto
+    "Struct",
+    "Value",
+    "NullValue",
+    "ListValue",
+    // timestamp.proto
+    "Timestamp",
+    // wrappers.proto
+    "DoubleValue",
+    "FloatValue",
+    "Int64Value",
+    "UInt64Value",
+    "Int32Value",
+    "UInt32Value",
+    "BoolValue",
+    "StringValue",
+    "BytesValue",
+    // api.proto, type.proto, source_context.proto
+    "Api",
+    "Method",
+    "Mixin",
+    "Type",
+    "Field",
+    "Enum",
+    "EnumValue",
+    "Option",
+    "Syntax",
+    "SourceContext",
+    // descriptor.proto
+    "FileDescriptorSet",
+    "FileDescriptorProto",
+    "DescriptorProto",
+    "ExtensionRangeOptions",
+    "FieldDescriptorProto",
+    "OneofDescriptorProto",
+    "EnumDescriptorProto",
+    "EnumValueDescriptorProto",
+    "ServiceDescriptorProto",
+    "MethodDescriptorProto",
+    "FileOptions",
+    "MessageOptions",
+    "FieldOptions",
+    "OneofOptions",
+    "EnumOptions",
+    "EnumValueOptions",
+    "ServiceOptions",
+    "MethodOptions",
+    "UninterpretedOption",
+    "FeatureSet",
+    "FeatureSetDefaults",
+    "SourceCodeInfo",
+    "GeneratedCodeInfo",
+    "Edition",
+    "SymbolVisibility",
+];
+
+/// Whether the fully-qualified `full_name` names a type declared by one of
+/// protoc's `google/protobuf` files, such as `google.protobuf.MethodOptions`
+/// or the nested `google.protobuf.FieldDescriptorProto.Type`.
+pub fn is_known_type(full_name: &str) -> bool {
+    full_name
+        .trim_start_matches('.')
+        .strip_prefix("google.protobuf.")
+        .and_then(|name| name.split('.').next())
+        .is_some_and(|name| TYPES.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_parse_embedded_well_known_types() {
+        for (name, source) in FILES {
+            let tokens = Lexer::new().lex(source).unwrap();
+            let proto = Parser::new(&tokens)
+                .parse()
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(proto.package.as_deref(), Some("google.protobuf"));
+        }
+
+        assert!(source("google/protobuf/timestamp.proto").is_some());
+        assert!(source("google/protobuf/descriptor.proto").is_none());
+        assert!(is_well_known(".google.protobuf.Timestamp"));
+        assert!(!is_well_known("google.api.HttpRule"));
+        assert!(is_known_type("google.protobuf.MethodOptions"));
+        assert!(is_known_type(".google.protobuf.FieldDescriptorProto.Type"));
+        assert!(!is_known_type("google.protobuf.Timestmp"));
+        assert!(!is_known_type("google.api.HttpRule"));
+    }
+}
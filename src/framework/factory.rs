@@ -1,5 +1,9 @@
-use crate::framework::{
-    type_mapper::{JavaTypeMapper, RustTypeMapper, TypeMapper}, Framework
+use crate::{
+    framework::{
+        Framework,
+        type_mapper::{JavaTypeMapper, RustTypeMapper, TypeMapper, TypeScriptTypeMapper},
+    },
+    parser::{Field, FieldKind},
 };
 
 pub struct TypeMapperFactory {
@@ -7,11 +11,50 @@ pub struct TypeMapperFactory {
 }
 
 impl TypeMapperFactory {
-    pub fn map_type(&self, proto_type: &str) -> String {
+    fn mapper(&self) -> &'static dyn TypeMapper {
         match self.framework {
-            Framework::Axum => RustTypeMapper.map_type(proto_type),
-            Framework::NestJS => "NOT IMPLEMENTED".to_string(),
-            Framework::Spring => JavaTypeMapper.map_type(proto_type),
+            Framework::Axum => &RustTypeMapper,
+            Framework::NestJS => &TypeScriptTypeMapper,
+            Framework::Spring => &JavaTypeMapper,
+        }
+    }
+
+    pub fn map_type(&self, proto_type: &str) -> String {
+        self.mapper().map_type(proto_type)
+    }
+
+    pub fn map_named(&self, proto_type: &str) -> String {
+        self.mapper().map_named(proto_type)
+    }
+
+    /// Maps a resolved field to the type used for it in the generated HTTP layer.
+    pub fn map_field(&self, field: &Field) -> String {
+        let mapper = self.mapper();
+        let element = self.map_element(field);
+
+        if let Some(key_type) = &field.key_type {
+            mapper.map_map(&mapper.map_type(key_type), &element)
+        } else if field.repeated {
+            mapper.map_repeated(&element)
+        } else if field.optional {
+            mapper.map_optional(&element)
+        } else {
+            element
+        }
+    }
+
+    /// Maps a `oneof` member; presence is carried by the group as a whole.
+    pub fn map_variant(&self, field: &Field) -> String {
+        self.mapper().map_variant(&self.map_element(field))
+    }
+
+    /// Maps the element type of a field: the value type of a map, or the
+    /// item type of a repeated field.
+    pub fn map_element(&self, field: &Field) -> String {
+        let mapper = self.mapper();
+        match field.kind {
+            FieldKind::Scalar => mapper.map_type(&field.proto_type),
+            FieldKind::Message | FieldKind::Enum => mapper.map_named(&field.proto_type),
         }
     }
 }
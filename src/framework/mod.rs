@@ -2,14 +2,15 @@ use std::{fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
-mod type_mapper;
 pub mod factory;
+mod type_mapper;
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
 pub enum Framework {
+    #[default]
     Axum,
     NestJS,
-    Spring
+    Spring,
 }
 
 impl FromStr for Framework {
@@ -36,12 +37,6 @@ impl fmt::Display for Framework {
     }
 }
 
-impl Default for Framework {
-    fn default() -> Self {
-        Self::Axum
-    }
-}
-
 impl Framework {
     pub fn all() -> Vec<Self> {
         vec![Self::Axum, Self::NestJS]
@@ -1,5 +1,23 @@
+use crate::scaffolder::filters::{to_capitalize, to_snake_case};
+
 pub trait TypeMapper {
+    /// Maps a proto type to the type generated for it by the framework's
+    /// protobuf toolchain (prost, protoc-gen-java, ...).
     fn map_type(&self, proto_type: &str) -> String;
+
+    /// Maps a message or enum name to the HTTP-side type mirroring it.
+    fn map_named(&self, proto_type: &str) -> String;
+
+    fn map_repeated(&self, element: &str) -> String;
+
+    fn map_map(&self, key: &str, value: &str) -> String;
+
+    fn map_optional(&self, inner: &str) -> String;
+
+    /// Maps the payload of a `oneof` member.
+    fn map_variant(&self, element: &str) -> String {
+        element.to_string()
+    }
 }
 
 pub struct RustTypeMapper;
@@ -8,18 +26,95 @@ impl TypeMapper for RustTypeMapper {
     fn map_type(&self, proto_type: &str) -> String {
         match proto_type {
             "string" => "String".to_string(),
-            "int32" | "sint32" | "fixed32" => "i32".to_string(),
-            "int64" | "sint64" | "fixed64" => "i64".to_string(),
+            "int32" | "sint32" | "sfixed32" => "i32".to_string(),
+            "int64" | "sint64" | "sfixed64" => "i64".to_string(),
             "bool" => "bool".to_string(),
             "float" => "f32".to_string(),
             "double" => "f64".to_string(),
             "bytes" => "Vec<u8>".to_string(),
-            "repeated" => "Vec<_>".to_string(),
-            "uint32" => "u32".to_string(),
-            "uint64" => "u64".to_string(),
-            _ => proto_type.to_string(),
+            "uint32" | "fixed32" => "u32".to_string(),
+            "uint64" | "fixed64" => "u64".to_string(),
+            _ => match well_known(proto_type) {
+                Some(name) => rust_wrapper(name)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("prost_types::{}", name)),
+                None => rust_type_path(proto_type),
+            },
+        }
+    }
+
+    fn map_named(&self, proto_type: &str) -> String {
+        match well_known(proto_type) {
+            Some(name) => rust_wrapper(name)
+                .filter(|_| name != "Empty")
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("wkt::{}", name)),
+            None => format!("Http{}", flat_name(proto_type)),
         }
     }
+
+    fn map_repeated(&self, element: &str) -> String {
+        format!("Vec<{}>", element)
+    }
+
+    fn map_map(&self, key: &str, value: &str) -> String {
+        format!("HashMap<{}, {}>", key, value)
+    }
+
+    fn map_optional(&self, inner: &str) -> String {
+        format!("Option<{}>", inner)
+    }
+}
+
+/// Returns the name of a `google.protobuf` well-known type, e.g. `Timestamp`
+/// for `.google.protobuf.Timestamp`.
+fn well_known(proto_type: &str) -> Option<&str> {
+    proto_type.strip_prefix(".google.protobuf.")
+}
+
+/// prost maps the wrapper types (and `Empty`) straight to Rust types, so a
+/// wrapper field is generated as `Option<T>`.
+fn rust_wrapper(name: &str) -> Option<&'static str> {
+    match name {
+        "BoolValue" => Some("bool"),
+        "BytesValue" => Some("Vec<u8>"),
+        "DoubleValue" => Some("f64"),
+        "FloatValue" => Some("f32"),
+        "Int32Value" => Some("i32"),
+        "Int64Value" => Some("i64"),
+        "StringValue" => Some("String"),
+        "UInt32Value" => Some("u32"),
+        "UInt64Value" => Some("u64"),
+        "Empty" => Some("()"),
+        _ => None,
+    }
+}
+
+/// Flattens a (possibly nested or package-qualified) type name into a single
+/// identifier, e.g. `Outer.Inner` to `OuterInner` and `.common.Money` to
+/// `CommonMoney`.
+fn flat_name(proto_type: &str) -> String {
+    proto_type
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(to_capitalize)
+        .collect()
+}
+
+/// Nested messages live in a module named after their parent in prost output,
+/// so `Outer.Inner` becomes `outer::Inner`. Types from other packages (with a
+/// leading dot) are addressed from the root of the generated modules.
+fn rust_type_path(proto_type: &str) -> String {
+    let (root, proto_type) = match proto_type.strip_prefix('.') {
+        Some(full_name) => ("crate::generated::", full_name),
+        None => ("", proto_type),
+    };
+    let mut segments: Vec<String> = proto_type.split('.').map(str::to_string).collect();
+    let last = segments.len() - 1;
+    for segment in &mut segments[..last] {
+        *segment = to_snake_case(segment);
+    }
+    format!("{}{}", root, segments.join("::"))
 }
 
 pub struct JavaTypeMapper;
@@ -28,16 +123,53 @@ impl TypeMapper for JavaTypeMapper {
     fn map_type(&self, proto_type: &str) -> String {
         match proto_type {
             "string" => "String".to_string(),
-            "int32" | "sint32" | "fixed32" | "uint32" => "int".to_string(),
-            "int64" | "sint64" | "fixed64" | "uint64" => "long".to_string(),
+            "int32" | "sint32" | "fixed32" | "uint32" | "sfixed32" => "int".to_string(),
+            "int64" | "sint64" | "fixed64" | "uint64" | "sfixed64" => "long".to_string(),
             "bool" => "boolean".to_string(),
             "float" => "float".to_string(),
             "double" => "double".to_string(),
             "bytes" => "byte[]".to_string(),
-            "repeated" => "List<...>".to_string(),
-            _ => proto_type.to_string(),
+            _ => match well_known(proto_type) {
+                Some(name) => format!("com.google.protobuf.{}", name),
+                None => proto_type.trim_start_matches('.').to_string(),
+            },
         }
     }
+
+    fn map_named(&self, proto_type: &str) -> String {
+        match well_known(proto_type) {
+            Some(name) => format!("WellKnown.{}", name),
+            None => flat_name(proto_type),
+        }
+    }
+
+    fn map_repeated(&self, element: &str) -> String {
+        format!("List<{}>", boxed(element))
+    }
+
+    fn map_map(&self, key: &str, value: &str) -> String {
+        format!("Map<{}, {}>", boxed(key), boxed(value))
+    }
+
+    fn map_optional(&self, inner: &str) -> String {
+        boxed(inner).to_string()
+    }
+
+    fn map_variant(&self, element: &str) -> String {
+        boxed(element).to_string()
+    }
+}
+
+/// Java generics cannot hold primitives.
+fn boxed(java_type: &str) -> &str {
+    match java_type {
+        "int" => "Integer",
+        "long" => "Long",
+        "boolean" => "Boolean",
+        "float" => "Float",
+        "double" => "Double",
+        other => other,
+    }
 }
 
 pub struct TypeScriptTypeMapper;
@@ -46,14 +178,162 @@ impl TypeMapper for TypeScriptTypeMapper {
     fn map_type(&self, proto_type: &str) -> String {
         match proto_type {
             "string" => "string".to_string(),
-            "int32" | "sint32" | "fixed32" | "uint32" => "number".to_string(),
-            "int64" | "sint64" | "fixed64" | "uint64" => "bigint".to_string(),
+            "int32" | "sint32" | "fixed32" | "uint32" | "sfixed32" => "number".to_string(),
+            "int64" | "sint64" | "fixed64" | "uint64" | "sfixed64" => "bigint".to_string(),
             "bool" => "boolean".to_string(),
             "float" => "number".to_string(),
             "double" => "number".to_string(),
             "bytes" => "Uint8Array".to_string(),
-            "repeated" => "Array<...>".to_string(),
-            _ => proto_type.to_string(),
+            _ => proto_type.trim_start_matches('.').to_string(),
+        }
+    }
+
+    fn map_named(&self, proto_type: &str) -> String {
+        match well_known(proto_type) {
+            Some(name) => typescript_well_known(name).to_string(),
+            None => flat_name(proto_type),
+        }
+    }
+
+    fn map_repeated(&self, element: &str) -> String {
+        format!("Array<{}>", element)
+    }
+
+    fn map_map(&self, key: &str, value: &str) -> String {
+        let key = if key == "bigint" { "string" } else { key };
+        format!("Record<{}, {}>", key, value)
+    }
+
+    fn map_optional(&self, inner: &str) -> String {
+        format!("{} | undefined", inner)
+    }
+}
+
+/// Canonical JSON forms of the well-known types; 64-bit integers are strings.
+fn typescript_well_known(name: &str) -> &'static str {
+    match name {
+        "Timestamp" | "Duration" | "FieldMask" => "string",
+        "Int64Value" | "UInt64Value" | "StringValue" | "BytesValue" => "string",
+        "Int32Value" | "UInt32Value" | "FloatValue" | "DoubleValue" => "number",
+        "BoolValue" => "boolean",
+        "Empty" => "Record<string, never>",
+        "Struct" => "Record<string, unknown>",
+        "ListValue" => "unknown[]",
+        "NullValue" => "null",
+        "Any" => "{ \"@type\": string; [key: string]: unknown }",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        framework::{Framework, factory::TypeMapperFactory},
+        parser::{Field, FieldKind},
+    };
+
+    fn field(proto_type: &str, kind: FieldKind) -> Field {
+        Field {
+            name: "value".to_string(),
+            type_: String::new(),
+            proto_type: proto_type.to_string(),
+            http_type: String::new(),
+            kind,
+            repeated: false,
+            optional: false,
+            key_type: None,
+            json_name: None,
+            deprecated: false,
+            description: None,
         }
     }
+
+    /// Maps `field` for Axum, Spring and NestJS, in that order.
+    fn map_all(field: &Field) -> [String; 3] {
+        [Framework::Axum, Framework::Spring, Framework::NestJS]
+            .map(|framework| TypeMapperFactory { framework }.map_field(field))
+    }
+
+    #[test]
+    fn test_map_well_known_types() {
+        let mut wrapper = field(".google.protobuf.Int64Value", FieldKind::Message);
+        wrapper.optional = true;
+        assert_eq!(
+            map_all(&wrapper),
+            ["Option<i64>", "WellKnown.Int64Value", "string | undefined"]
+        );
+
+        let timestamp = field(".google.protobuf.Timestamp", FieldKind::Message);
+        assert_eq!(
+            map_all(&timestamp),
+            ["wkt::Timestamp", "WellKnown.Timestamp", "string"]
+        );
+        assert_eq!(
+            RustTypeMapper.map_type(".google.protobuf.Timestamp"),
+            "prost_types::Timestamp"
+        );
+        assert_eq!(
+            JavaTypeMapper.map_type(".google.protobuf.Timestamp"),
+            "com.google.protobuf.Timestamp"
+        );
+
+        let empty = field(".google.protobuf.Empty", FieldKind::Message);
+        assert_eq!(
+            map_all(&empty),
+            ["wkt::Empty", "WellKnown.Empty", "Record<string, never>"]
+        );
+        assert_eq!(RustTypeMapper.map_type(".google.protobuf.Empty"), "()");
+    }
+
+    #[test]
+    fn test_map_maps() {
+        let mut messages = field("Foo", FieldKind::Message);
+        messages.key_type = Some("string".to_string());
+        assert_eq!(
+            map_all(&messages),
+            [
+                "HashMap<String, HttpFoo>",
+                "Map<String, Foo>",
+                "Record<string, Foo>"
+            ]
+        );
+
+        let mut counts = field("int32", FieldKind::Scalar);
+        counts.key_type = Some("int64".to_string());
+        assert_eq!(
+            map_all(&counts),
+            [
+                "HashMap<i64, i32>",
+                "Map<Long, Integer>",
+                "Record<string, number>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_presence_and_repeated() {
+        let mut optional = field("int32", FieldKind::Scalar);
+        optional.optional = true;
+        assert_eq!(
+            map_all(&optional),
+            ["Option<i32>", "Integer", "number | undefined"]
+        );
+
+        let mut repeated = field("Outer.Inner", FieldKind::Message);
+        repeated.repeated = true;
+        assert_eq!(
+            map_all(&repeated),
+            [
+                "Vec<HttpOuterInner>",
+                "List<OuterInner>",
+                "Array<OuterInner>"
+            ]
+        );
+        assert_eq!(RustTypeMapper.map_type("Outer.Inner"), "outer::Inner");
+        assert_eq!(
+            RustTypeMapper.map_type(".common.v1.Money"),
+            "crate::generated::common::v1::Money"
+        );
+    }
 }
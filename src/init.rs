@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+
+use proto_def::{
+    model::{Message, RpcMethod},
+    resolver::Resolver,
+    validate,
+};
+use serde::Serialize;
+
+use crate::{parser::factory::route_path, scaffolder::filters::to_snake_case};
+
+const SCHEMA: &str = "https://zeann3th.github.io/havoc/schemas/v0.1/config.json";
+
+/// A starting config written by `havoc init`, holding only what the schema
+/// allows so that it validates as written.
+#[derive(Debug, Serialize)]
+pub struct InitConfig {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    metadata: InitMetadata,
+    spec: InitSpec,
+}
+
+impl InitConfig {
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_yml::to_string(self)?)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InitMetadata {
+    name: String,
+    version: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InitSpec {
+    host: String,
+    port: u16,
+    #[serde(rename = "protoPaths", skip_serializing_if = "Vec::is_empty")]
+    proto_paths: Vec<String>,
+    services: Vec<InitService>,
+}
+
+#[derive(Debug, Serialize)]
+struct InitService {
+    name: String,
+    proto: String,
+    url: String,
+    endpoints: Vec<InitEndpoint>,
+}
+
+#[derive(Debug, Serialize)]
+struct InitEndpoint {
+    rpc: String,
+    method: String,
+    path: String,
+}
+
+/// Builds a config exposing every service declared in `protos`, with
+/// `proto_paths` searched for their imports. Endpoints follow the RPCs'
+/// `google.api.http` annotations, or are guessed from the RPC names.
+pub fn init(
+    protos: &[String],
+    proto_paths: Vec<String>,
+    url: &str,
+) -> Result<InitConfig, Box<dyn std::error::Error>> {
+    let mut services = Vec::new();
+    let mut routes = Vec::new();
+    for proto in protos {
+        let mut include_paths: Vec<PathBuf> = proto_paths.iter().map(PathBuf::from).collect();
+        include_paths.push(match Path::new(proto).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        });
+        let set = Resolver::new(include_paths).resolve(Path::new(proto))?;
+        validate::validate(&set)?;
+
+        let root = set.root();
+        let scope = root.package.clone().unwrap_or_default();
+        for svc in &root.services {
+            let mut rpcs: Vec<_> = svc.methods.values().collect();
+            rpcs.sort_by_key(|rpc| rpc.span.start);
+            let mut endpoints = Vec::new();
+            for rpc in rpcs {
+                let (method, mut path) = match &rpc.http {
+                    Some(rule) => {
+                        let path = route_path(&rule.path).map_err(|e| {
+                            format!("{} in the google.api.http rule of RPC '{}'", e, rpc.name)
+                        })?;
+                        (rule.method.clone(), path)
+                    }
+                    None => {
+                        let request = set.resolve_message(&scope, &rpc.request);
+                        guess_route(&svc.name, rpc, request)
+                    }
+                };
+                // Two RPCs guessed onto one route would clash in the router.
+                if routes.contains(&(method.clone(), path.clone())) {
+                    path = rpc_path(&svc.name, &rpc.name);
+                }
+                routes.push((method.clone(), path.clone()));
+                endpoints.push(InitEndpoint {
+                    rpc: rpc.name.clone(),
+                    method,
+                    path,
+                });
+            }
+
+            services.push(InitService {
+                name: svc.name.clone(),
+                proto: proto.clone(),
+                url: url.to_string(),
+                endpoints,
+            });
+        }
+    }
+    if services.is_empty() {
+        return Err("No services found in the given proto files".into());
+    }
+
+    Ok(InitConfig {
+        schema: SCHEMA,
+        metadata: InitMetadata {
+            name: "gateway".to_string(),
+            version: "0.1.0".to_string(),
+        },
+        spec: InitSpec {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            proto_paths,
+            services,
+        },
+    })
+}
+
+/// Guesses a REST method and path from an RPC name, e.g. `GetBook` becomes
+/// `GET /books/{id}`, `ListBooks` becomes `GET /books` and `CreateBook`
+/// becomes `POST /books`. Unrecognized names are posted to
+/// `/<service>/<rpc>`.
+fn guess_route(service: &str, rpc: &RpcMethod, request: Option<&Message>) -> (String, String) {
+    const VERBS: &[(&str, &str)] = &[
+        ("Get", "GET"),
+        ("List", "GET"),
+        ("Search", "GET"),
+        ("Create", "POST"),
+        ("Add", "POST"),
+        ("Update", "PUT"),
+        ("Replace", "PUT"),
+        ("Patch", "PATCH"),
+        ("Delete", "DELETE"),
+        ("Remove", "DELETE"),
+    ];
+
+    let verb = VERBS.iter().find_map(|(prefix, method)| {
+        let resource = rpc.name.strip_prefix(prefix)?;
+        let word_ends = resource.chars().next().is_none_or(char::is_uppercase);
+        word_ends.then_some((*prefix, *method, resource))
+    });
+    let Some((prefix, method, resource)) = verb else {
+        return ("POST".to_string(), rpc_path(service, &rpc.name));
+    };
+
+    let resource = if resource.is_empty() {
+        service
+    } else {
+        resource
+    };
+    let path = match prefix {
+        "List" => format!("/{}", kebab_case(resource)),
+        "Search" => format!("/{}/search", kebab_case(resource)),
+        "Create" | "Add" => format!("/{}", pluralize(&kebab_case(resource))),
+        _ => {
+            let collection = pluralize(&kebab_case(resource));
+            let id_field = format!("{}_id", to_snake_case(resource));
+            let key = request.and_then(|message| {
+                ["id", "name", id_field.as_str()]
+                    .into_iter()
+                    .find(|key| message.fields.iter().any(|f| f.name == *key))
+            });
+            match key {
+                Some(key) => format!("/{}/{{{}}}", collection, key),
+                None => format!("/{}", collection),
+            }
+        }
+    };
+    (method.to_string(), path)
+}
+
+/// `/<service>/<rpc>`, e.g. `/auth/login`.
+fn rpc_path(service: &str, rpc: &str) -> String {
+    format!("/{}/{}", kebab_case(service), kebab_case(rpc))
+}
+
+fn kebab_case(name: &str) -> String {
+    to_snake_case(name).replace('_', "-")
+}
+
+/// A naive English plural, e.g. `books`, `categories` or `addresses`.
+fn pluralize(name: &str) -> String {
+    let consonant_y =
+        name.ends_with('y') && !name[..name.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+    if consonant_y {
+        format!("{}ies", &name[..name.len() - 1])
+    } else if name.ends_with(['x', 'z']) || ["ss", "ch", "sh"].iter().any(|e| name.ends_with(e)) {
+        format!("{}es", name)
+    } else if name.ends_with('s') {
+        // Most likely plural already, e.g. `stats`.
+        name.to_string()
+    } else {
+        format!("{}s", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use proto_def::{lexer::Lexer, model::Proto, parser::Parser};
+
+    use super::*;
+
+    fn parse(source: &str) -> Proto {
+        let tokens = Lexer::new().lex(source).unwrap();
+        Parser::new(&tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_guess_route() {
+        let proto = parse(
+            r#"
+            syntax = "proto3";
+            service Library {
+                rpc GetBook (GetBookRequest) returns (Book);
+                rpc GetOrder (GetOrderRequest) returns (Order);
+                rpc ListBooks (Empty) returns (Books);
+                rpc SearchBooks (Empty) returns (Books);
+                rpc AddCategory (Empty) returns (Empty);
+                rpc DeleteBox (Empty) returns (Empty);
+                rpc Getaway (Empty) returns (Empty);
+                rpc Get (GetBookRequest) returns (Empty);
+            }
+            message GetBookRequest { string id = 1; }
+            message GetOrderRequest { string order_id = 1; }
+        "#,
+        );
+        let svc = &proto.services[0];
+        let route = |rpc: &str, request: Option<&str>| {
+            let request = request.and_then(|name| proto.messages.iter().find(|m| m.name == name));
+            let (method, path) = guess_route(&svc.name, &svc.methods[rpc], request);
+            format!("{} {}", method, path)
+        };
+
+        assert_eq!(route("GetBook", Some("GetBookRequest")), "GET /books/{id}");
+        assert_eq!(
+            route("GetOrder", Some("GetOrderRequest")),
+            "GET /orders/{order_id}"
+        );
+        assert_eq!(route("ListBooks", None), "GET /books");
+        assert_eq!(route("SearchBooks", None), "GET /books/search");
+        assert_eq!(route("AddCategory", None), "POST /categories");
+        assert_eq!(route("DeleteBox", None), "DELETE /boxes");
+        assert_eq!(route("Getaway", None), "POST /library/getaway");
+        assert_eq!(route("Get", Some("GetBookRequest")), "GET /libraries/{id}");
+    }
+
+    #[test]
+    fn test_pluralize() {
+        assert_eq!(pluralize("book"), "books");
+        assert_eq!(pluralize("category"), "categories");
+        assert_eq!(pluralize("day"), "days");
+        assert_eq!(pluralize("address"), "addresses");
+        assert_eq!(pluralize("box"), "boxes");
+        assert_eq!(pluralize("branch"), "branches");
+        assert_eq!(pluralize("stats"), "stats");
+    }
+
+    #[test]
+    fn test_init_renames_duplicate_routes() {
+        let dir = std::env::temp_dir().join(format!("havoc_init_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let proto = dir.join("books.proto");
+        fs::write(
+            &proto,
+            r#"
+            syntax = "proto3";
+            service Books {
+                rpc ListBooks (Empty) returns (Empty);
+                rpc GetBook (Empty) returns (Empty);
+                rpc Login (Empty) returns (Empty);
+            }
+            message Empty {}
+        "#,
+        )
+        .unwrap();
+
+        let proto = proto.to_str().unwrap().to_string();
+        let config = init(&[proto], Vec::new(), "http://localhost:50051").unwrap();
+        let routes: Vec<_> = config.spec.services[0]
+            .endpoints
+            .iter()
+            .map(|e| format!("{} {} {}", e.rpc, e.method, e.path))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                "ListBooks GET /books",
+                "GetBook GET /books/get-book",
+                "Login POST /books/login",
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use proto_def::{
+    model::{Enum, Field, Label, Message, Proto},
+    resolver::Resolver,
+};
+use serde_json::json;
+
+use crate::parser::{self, Config, factory::load_protos};
+
+/// Output of `havoc inspect`.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Tree,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tree" => Ok(Self::Tree),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unsupported format: {}", s)),
+        }
+    }
+}
+
+/// Renders what havoc sees for each service of `config`: the root proto file
+/// declaring it, then the service as populated from it. As JSON, this is
+/// `{"protos": {<service>: <proto>}, "config": <config>}`.
+pub fn inspect(config: &Config, format: Format) -> Result<String, Box<dyn std::error::Error>> {
+    let resolver = Resolver::new(config.spec.include_paths());
+    let mut protos = Vec::new();
+    for service in &config.spec.services {
+        protos.push(load_protos(&resolver, service)?);
+    }
+
+    match format {
+        Format::Json => {
+            let by_service: HashMap<_, _> = config
+                .spec
+                .services
+                .iter()
+                .zip(&protos)
+                .map(|(service, set)| (service.name.as_str(), set.root()))
+                .collect();
+            let value = json!({ "protos": by_service, "config": config });
+            Ok(serde_json::to_string_pretty(&value)?)
+        }
+        Format::Tree => {
+            let trees: Vec<_> = config
+                .spec
+                .services
+                .iter()
+                .zip(&protos)
+                .flat_map(|(service, set)| {
+                    [
+                        proto_node(&set.files[0].name, set.root()),
+                        service_node(service),
+                    ]
+                })
+                .map(|node| node.to_string())
+                .collect();
+            Ok(trees.join("\n"))
+        }
+    }
+}
+
+/// A labelled node of the tree output.
+struct Node {
+    label: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(label: impl Into<String>) -> Self {
+        Node {
+            label: label.into(),
+            children: Vec::new(),
+        }
+    }
+
+    fn with(mut self, children: impl IntoIterator<Item = Node>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    fn write_children(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            writeln!(f, "{}{}{}", prefix, branch, child.label)?;
+            child.write_children(f, &format!("{}{}", prefix, indent))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.label)?;
+        self.write_children(f, "")
+    }
+}
+
+fn option_nodes(options: &HashMap<String, String>) -> Vec<Node> {
+    let mut options: Vec<_> = options.iter().collect();
+    options.sort();
+    options
+        .into_iter()
+        .map(|(name, value)| Node::new(format!("option {} = {}", name, value)))
+        .collect()
+}
+
+fn proto_node(name: &str, proto: &Proto) -> Node {
+    let syntax = match (&proto.edition, &proto.syntax) {
+        (Some(edition), _) => format!("edition {}", edition),
+        (None, Some(syntax)) => syntax.clone(),
+        (None, None) => "proto2".to_string(),
+    };
+    let mut node = Node::new(format!("proto {} ({})", name, syntax));
+    if let Some(package) = &proto.package {
+        node.children
+            .push(Node::new(format!("package {}", package)));
+    }
+    node.children.extend(
+        proto
+            .imports
+            .iter()
+            .map(|import| Node::new(format!("import {}", import))),
+    );
+    node.children.extend(option_nodes(&proto.options));
+
+    for service in &proto.services {
+        let mut methods: Vec<_> = service.methods.values().collect();
+        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        let methods = methods.into_iter().map(|rpc| {
+            let stream = |streaming: bool| if streaming { "stream " } else { "" };
+            let mut node = Node::new(format!(
+                "rpc {} ({}{}) returns ({}{})",
+                rpc.name,
+                stream(rpc.client_streaming),
+                rpc.request,
+                stream(rpc.server_streaming),
+                rpc.response
+            ));
+            if let Some(http) = &rpc.http {
+                let body = http
+                    .body
+                    .as_ref()
+                    .map(|body| format!(" body {}", body))
+                    .unwrap_or_default();
+                node.children.push(Node::new(format!(
+                    "http {} {}{}",
+                    http.method, http.path, body
+                )));
+            }
+            node.with(option_nodes(&rpc.options))
+        });
+        node.children
+            .push(Node::new(format!("service {}", service.name)).with(methods));
+    }
+    node.children
+        .extend(proto.messages.iter().map(message_node));
+    node.children.extend(proto.enums.iter().map(enum_node));
+    for extend in &proto.extends {
+        node.children.push(
+            Node::new(format!("extend {}", extend.extendee))
+                .with(extend.fields.iter().map(field_node)),
+        );
+    }
+    node
+}
+
+fn message_node(message: &Message) -> Node {
+    Node::new(format!("message {}", message.full_name))
+        .with(option_nodes(&message.options))
+        .with(message.fields.iter().map(field_node))
+        .with(message.oneofs.iter().map(|oneof| {
+            Node::new(format!("oneof {}", oneof.name)).with(oneof.fields.iter().map(field_node))
+        }))
+        .with(message.messages.iter().map(message_node))
+        .with(message.enums.iter().map(enum_node))
+}
+
+fn enum_node(en: &Enum) -> Node {
+    Node::new(format!("enum {}", en.full_name)).with(
+        en.values
+            .iter()
+            .map(|value| Node::new(format!("{} = {}", value.name, value.number))),
+    )
+}
+
+fn field_node(field: &Field) -> Node {
+    let label = match (field.repeated, field.label) {
+        (true, _) => "repeated ",
+        (false, Some(Label::Optional)) => "optional ",
+        (false, Some(Label::Required)) => "required ",
+        (false, None) => "",
+    };
+    let field_type = match &field.key_type {
+        Some(key) => format!("map<{}, {}>", key, field.field_type),
+        None => field.field_type.clone(),
+    };
+    Node::new(format!(
+        "{}{} {} = {}",
+        label, field_type, field.name, field.number
+    ))
+    .with(option_nodes(&field.options))
+}
+
+fn service_node(service: &parser::Service) -> Node {
+    let endpoints = service.endpoints.iter().map(|e| {
+        let mut rpc = e.rpc.clone();
+        if e.client_streaming || e.server_streaming {
+            rpc.push_str(" (streaming)");
+        }
+        if e.auth {
+            rpc.push_str(" (auth)");
+        }
+        Node::new(format!("endpoint {} {} -> {}", e.method, e.path, rpc)).with([
+            Node::new(format!(
+                "request {} as {}",
+                e.request.type_, e.request.http_type
+            ))
+            .with(e.request.fields.iter().map(config_field_node)),
+            Node::new(format!(
+                "response {} as {}",
+                e.response.type_, e.response.http_type
+            ))
+            .with(e.response.fields.iter().map(config_field_node)),
+        ])
+    });
+    let messages = service.messages.iter().map(|m| {
+        Node::new(format!("message {} as {}", m.type_, m.http_type))
+            .with(m.fields.iter().map(config_field_node))
+            .with(m.oneofs.iter().map(|o| {
+                Node::new(format!("oneof {} as {}", o.name, o.http_type))
+                    .with(o.fields.iter().map(config_field_node))
+            }))
+    });
+    let enums = service.enums.iter().map(|en| {
+        Node::new(format!("enum {} as {}", en.type_, en.http_type)).with(
+            en.values
+                .iter()
+                .map(|value| Node::new(format!("{} = {}", value.name, value.number))),
+        )
+    });
+
+    Node::new(format!("service {} -> {}", service.name, service.url))
+        .with(endpoints)
+        .with(messages)
+        .with(enums)
+}
+
+fn config_field_node(field: &parser::Field) -> Node {
+    Node::new(format!(
+        "{}: {} (proto {})",
+        field.name, field.type_, field.proto_type
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::parser::factory::ParserFactory;
+
+    #[test]
+    fn test_inspect_json() {
+        let dir = std::env::temp_dir().join(format!("havoc_inspect_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let proto = dir.join("shop.proto");
+        fs::write(
+            &proto,
+            r#"
+            syntax = "proto3";
+            package shop.v1;
+            service Shop { rpc GetItem (GetItemRequest) returns (Item); }
+            message GetItemRequest { string id = 1; }
+            message Item { string id = 1; repeated string tags = 2; }
+        "#,
+        )
+        .unwrap();
+        let content = format!(
+            r#"{{
+                "metadata": {{"name": "shop", "version": "1"}},
+                "spec": {{
+                    "host": "127.0.0.1",
+                    "port": 3000,
+                    "services": [{{
+                        "name": "Shop",
+                        "proto": {:?},
+                        "url": "http://localhost:50051",
+                        "endpoints": [{{"rpc": "GetItem", "method": "get", "path": "/items/{{id}}"}}]
+                    }}]
+                }}
+            }}"#,
+            proto.to_str().unwrap()
+        );
+        let config = ParserFactory::from_content("havoc.json", content)
+            .and_then(|factory| factory.build())
+            .unwrap();
+
+        let output = inspect(&config, Format::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let proto = &value["protos"]["Shop"];
+        assert_eq!(proto["package"], "shop.v1");
+        assert_eq!(proto["services"][0]["name"], "Shop");
+        assert_eq!(proto["messages"][1]["fields"][1]["name"], "tags");
+        assert_eq!(proto["messages"][1]["fields"][1]["repeated"], true);
+        let endpoint = &value["config"]["spec"]["services"][0]["endpoints"][0];
+        assert_eq!(endpoint["method"], "GET");
+        assert_eq!(endpoint["request"]["pathParams"], serde_json::json!(["id"]));
+
+        let tree = inspect(&config, Format::Tree).unwrap();
+        assert!(tree.contains("endpoint GET /items/{id} -> GetItem"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
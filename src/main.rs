@@ -1,9 +1,12 @@
 use clap::{Parser, Subcommand};
-use std::path::Path;
+use proto_def::printer;
+use std::{fs, path::Path};
 
 use crate::{parser::factory::ParserFactory, scaffolder::factory::ScaffolderFactory};
 
 mod framework;
+mod init;
+mod inspect;
 mod parser;
 mod scaffolder;
 
@@ -16,7 +19,6 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
-    #[command(alias = "new")]
     New {
         #[arg(value_name = "CONFIG_PATH")]
         config_path: String,
@@ -26,16 +28,84 @@ enum Command {
 
         #[arg(short = 'f', long, value_name = "FRAMEWORK", default_value = "axum")]
         framework: framework::Framework,
+
+        /// Directory to search for proto imports, may be repeated
+        #[arg(short = 'I', long = "proto-path", value_name = "DIR")]
+        proto_paths: Vec<String>,
+    },
+
+    /// Write a config exposing the services of existing proto files
+    Init {
+        /// Proto file to read services from, may be repeated
+        #[arg(long = "proto", value_name = "PROTO_FILE", required = true)]
+        protos: Vec<String>,
+
+        /// Config file to write, as JSON or YAML by its extension
+        #[arg(short, long, value_name = "CONFIG_PATH", default_value = "havoc.yaml")]
+        output: String,
+
+        /// gRPC URL of the services
+        #[arg(long, value_name = "URL", default_value = "http://localhost:50051")]
+        url: String,
+
+        /// Directory to search for proto imports, may be repeated
+        #[arg(short = 'I', long = "proto-path", value_name = "DIR")]
+        proto_paths: Vec<String>,
+
+        /// Overwrite the config file if it exists
+        #[arg(long)]
+        force: bool,
     },
 
     #[command(alias = "val")]
     Validate {
         #[arg(value_name = "CONFIG_PATH")]
         config_path: String,
+
+        /// Directory to search for proto imports, may be repeated
+        #[arg(short = 'I', long = "proto-path", value_name = "DIR")]
+        proto_paths: Vec<String>,
+    },
+
+    /// Print the parsed protos and the resolved config
+    Inspect {
+        #[arg(value_name = "CONFIG_PATH")]
+        config_path: String,
+
+        /// Output as a `tree` or as `json`
+        #[arg(long, value_name = "FORMAT", default_value = "tree")]
+        format: inspect::Format,
+
+        /// Framework whose types the fields are mapped to
+        #[arg(short = 'f', long, value_name = "FRAMEWORK", default_value = "axum")]
+        framework: framework::Framework,
+
+        /// Directory to search for proto imports, may be repeated
+        #[arg(short = 'I', long = "proto-path", value_name = "DIR")]
+        proto_paths: Vec<String>,
     },
 
     #[command(alias = "list-fw")]
     ListFrameworks,
+
+    /// Work with proto files
+    Proto {
+        #[command(subcommand)]
+        command: ProtoCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProtoCommand {
+    /// Print a proto file in canonical form, keeping its comments
+    Fmt {
+        #[arg(value_name = "PROTO_FILE")]
+        file: String,
+
+        /// Rewrite the file instead of printing it
+        #[arg(short, long)]
+        write: bool,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -46,31 +116,75 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             config_path,
             output,
             framework,
+            proto_paths,
         } => {
-            let parser = ParserFactory::with_framework(framework, &config_path)?;
-            let config = parser.build()?;
+            let parser = ParserFactory::with_framework(framework, &config_path)?
+                .with_proto_paths(proto_paths);
+            let config = or_exit(parser.build());
 
             for service in &config.spec.services {
-                if !Path::new(&service.proto).exists() {
-                    return Err(format!("Proto file {} does not exist.", service.proto).into());
+                if !Path::new(service.source()).exists() {
+                    return Err(format!("Proto file {} does not exist.", service.source()).into());
                 }
             }
 
             let output = Path::new(&output);
             let scaffolder_factory = ScaffolderFactory { framework, config };
-            scaffolder_factory.scaffold(output)?;
+            or_exit(scaffolder_factory.scaffold(output));
 
             println!("✅ Project generated at `{}`", output.display());
             Ok(())
         }
 
-        Command::Validate { config_path } => {
-            let parser = ParserFactory::new(&config_path)?;
-            parser.build()?;
+        Command::Init {
+            protos,
+            output,
+            url,
+            proto_paths,
+            force,
+        } => {
+            if Path::new(&output).exists() && !force {
+                return Err(
+                    format!("{} already exists, pass --force to overwrite it", output).into(),
+                );
+            }
+
+            let config = or_exit(init::init(&protos, proto_paths, &url));
+            let content = match Path::new(&output).extension().and_then(|s| s.to_str()) {
+                Some("json") => config.to_json()?,
+                _ => config.to_yaml()?,
+            };
+            // The guessed endpoints must resolve against the protos.
+            or_exit(ParserFactory::from_content(&output, content.clone()).and_then(|p| p.build()));
+            fs::write(&output, content)?;
+
+            println!("✅ Config written to `{}`", output);
+            Ok(())
+        }
+
+        Command::Validate {
+            config_path,
+            proto_paths,
+        } => {
+            let parser = ParserFactory::new(&config_path)?.with_proto_paths(proto_paths);
+            or_exit(parser.build());
             println!("✅ Configuration is valid.");
             Ok(())
         }
 
+        Command::Inspect {
+            config_path,
+            format,
+            framework,
+            proto_paths,
+        } => {
+            let parser = ParserFactory::with_framework(framework, &config_path)?
+                .with_proto_paths(proto_paths);
+            let config = or_exit(parser.build());
+            println!("{}", or_exit(inspect::inspect(&config, format)));
+            Ok(())
+        }
+
         Command::ListFrameworks => {
             println!("Available frameworks:");
             for fw in framework::Framework::all() {
@@ -78,5 +192,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             Ok(())
         }
+
+        Command::Proto {
+            command: ProtoCommand::Fmt { file, write },
+        } => {
+            let content = fs::read_to_string(&file)?;
+            let formatted =
+                or_exit(printer::format(Path::new(&file), &content).map_err(Into::into));
+            if write {
+                fs::write(&file, formatted)?;
+            } else {
+                print!("{}", formatted);
+            }
+            Ok(())
+        }
     }
 }
+
+/// Unwraps `result`, or prints the error and exits with status 1. Returning
+/// it from `main` would print its `Debug` form, which for proto errors dumps
+/// the whole file instead of a snippet pointing at the offending token.
+fn or_exit<T>(result: Result<T, Box<dyn std::error::Error>>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
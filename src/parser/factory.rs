@@ -1,25 +1,42 @@
 use std::str::FromStr;
 use std::{fs, path::Path};
 
-use proto_def::{lexer::Lexer, parser::Parser as ProtoParser};
+use proto_def::{
+    descriptor,
+    model::{self, Definition, is_scalar},
+    resolver::{ProtoSet, Resolver},
+    validate, well_known,
+};
 
 use crate::{
     framework::{Framework, factory::TypeMapperFactory},
     parser::{
-        Config, Field, Parser,
+        Config, Endpoint, Enum, EnumValue, Field, FieldKind, Headers, Message, Oneof, Parser,
+        Request, Service,
         formats::{FileFormat, json::JsonParser, yaml::YamlParser},
     },
+    scaffolder::filters::to_pascal_case,
 };
 
 pub struct ParserFactory {
     pub file_type: FileFormat,
     pub content: String,
     pub framework: Framework,
+    pub proto_paths: Vec<String>,
 }
 
 impl ParserFactory {
     pub fn new(config_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(&config_path)?;
+        let content = fs::read_to_string(config_path)?;
+        Self::from_content(config_path, content)
+    }
+
+    /// Parses `content` as the config at `config_path`, whose extension gives
+    /// its format.
+    pub fn from_content(
+        config_path: &str,
+        content: String,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let ext = Path::new(config_path)
             .extension()
             .and_then(|s| s.to_str())
@@ -31,6 +48,7 @@ impl ParserFactory {
             file_type,
             content,
             framework: Framework::default(),
+            proto_paths: Vec::new(),
         })
     }
 
@@ -43,8 +61,19 @@ impl ParserFactory {
         Ok(factory)
     }
 
+    /// Include paths given on the command line, searched before the config's
+    /// `protoPaths`.
+    pub fn with_proto_paths(mut self, proto_paths: Vec<String>) -> Self {
+        self.proto_paths = proto_paths;
+        self
+    }
+
     pub fn build(&self) -> Result<Config, Box<dyn std::error::Error>> {
         let mut config = self.parse()?;
+        config
+            .spec
+            .proto_paths
+            .splice(0..0, self.proto_paths.iter().cloned());
         populate_from_proto(&mut config)?;
         map_field_types(&mut config, self.framework);
         Ok(config)
@@ -60,15 +89,23 @@ impl ParserFactory {
 }
 
 fn populate_from_proto(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let resolver = Resolver::new(config.spec.include_paths());
+    let auth_option = config.spec.auth_option.as_deref().map(option_name);
+
     for service in &mut config.spec.services {
-        let proto_path = &service.proto;
-        let content = fs::read_to_string(proto_path)?;
-        let lexer = Lexer::new();
-        let tokens = lexer
-            .lex(&content)
-            .map_err(|e| format!("Lexing error: {}", e))?;
-        let mut parser = ProtoParser::new(&tokens);
-        let proto = parser.parse()?;
+        let protos = load_protos(&resolver, service)?;
+        let proto = protos.root();
+        if let (Some(option), Some(path)) = (auth_option, service.descriptor()) {
+            // Without the declaration the option cannot be decoded, and every
+            // endpoint would quietly go unauthenticated.
+            if !declares_method_option(&protos, option) {
+                return Err(format!(
+                    "Auth option '{}' is not declared in descriptor set '{}'; build it with --include_imports",
+                    option, path
+                )
+                .into());
+            }
+        }
 
         let svc = proto
             .services
@@ -77,13 +114,21 @@ fn populate_from_proto(config: &mut Config) -> Result<(), Box<dyn std::error::Er
             .ok_or_else(|| {
                 format!(
                     "Service '{}' not found in proto file '{}'",
-                    service.name, proto_path
+                    service.name, service.proto
                 )
             })?;
 
         for (k, v) in proto.options.iter() {
             service.options.entry(k.clone()).or_insert(v.clone());
         }
+        service.package = proto.package.clone().unwrap_or_default();
+        if service.description.is_none() {
+            service.description = svc.comments.doc();
+        }
+
+        if service.endpoints.is_empty() {
+            service.endpoints = annotated_endpoints(svc);
+        }
 
         for endpoint in &mut service.endpoints {
             let rpc = svc.methods.get(&endpoint.rpc).ok_or_else(|| {
@@ -93,59 +138,1143 @@ fn populate_from_proto(config: &mut Config) -> Result<(), Box<dyn std::error::Er
                 )
             })?;
 
-            if endpoint.request.type_.is_empty() {
-                endpoint.request.type_ = rpc.request.clone();
+            endpoint.client_streaming = rpc.client_streaming;
+            endpoint.server_streaming = rpc.server_streaming;
+            if let Some(option) = auth_option {
+                let service_name = match &proto.package {
+                    Some(package) => format!("{}.{}", package, svc.name),
+                    None => svc.name.clone(),
+                };
+                let options = protos.custom_options(&service_name, rpc);
+                endpoint.auth |= options.get(option) == Some(&"true");
             }
-            if endpoint.response.type_.is_empty() {
-                endpoint.response.type_ = rpc.response.clone();
+            if endpoint.description.is_none() {
+                endpoint.description = rpc.comments.doc();
             }
 
-            let req_msg = proto
-                .messages
-                .iter()
-                .find(|m| m.name == rpc.request)
+            let scope = proto.package.clone().unwrap_or_default();
+
+            let req_msg = protos
+                .resolve_message(&scope, &rpc.request)
                 .ok_or_else(|| format!("Request message '{}' not found", rpc.request))?;
+            if endpoint.request.type_.is_empty() {
+                endpoint.request.type_ = type_name(&protos, &req_msg.full_name);
+            }
+            endpoint.request.fields = resolve_fields(&protos, req_msg, &req_msg.fields);
+            endpoint.method = endpoint.method.to_uppercase();
+            endpoint.path = route_path(&endpoint.path).map_err(|e| {
+                format!(
+                    "{} in path '{}' of RPC '{}'",
+                    e, endpoint.path, endpoint.rpc
+                )
+            })?;
+            bind_request(endpoint, &service.headers, rpc.client_streaming).map_err(|e| {
+                format!(
+                    "{} for {} {} of RPC '{}' (request '{}')",
+                    e, endpoint.method, endpoint.path, endpoint.rpc, req_msg.full_name
+                )
+            })?;
+            collect_definitions(&protos, req_msg, &mut service.messages, &mut service.enums);
 
-            endpoint.request.fields = req_msg
-                .fields
-                .iter()
-                .map(|f| Field {
-                    name: f.name.clone(),
-                    type_: f.field_type.clone(),
-                })
-                .collect();
-
-            let res_msg = proto
-                .messages
-                .iter()
-                .find(|m| m.name == rpc.response)
+            let res_msg = protos
+                .resolve_message(&scope, &rpc.response)
                 .ok_or_else(|| format!("Response message '{}' not found", rpc.response))?;
+            if endpoint.response.type_.is_empty() {
+                endpoint.response.type_ = type_name(&protos, &res_msg.full_name);
+            }
+            endpoint.response.fields = resolve_fields(&protos, res_msg, &res_msg.fields);
+            collect_definitions(&protos, res_msg, &mut service.messages, &mut service.enums);
+        }
+    }
 
-            endpoint.response.fields = res_msg
-                .fields
-                .iter()
-                .map(|f| Field {
-                    name: f.name.clone(),
-                    type_: f.field_type.clone(),
-                })
-                .collect();
+    Ok(())
+}
+
+/// Loads the protos declaring `service`, from its descriptor set or its
+/// `.proto` sources, with the file declaring the service as the root.
+pub fn load_protos(
+    resolver: &Resolver,
+    service: &Service,
+) -> Result<ProtoSet, Box<dyn std::error::Error>> {
+    match service.descriptor() {
+        // protoc already checked a compiled set, so it is not validated again.
+        Some(path) => {
+            let mut protos = descriptor::load(Path::new(path))?;
+            let root = match service.proto_descriptor {
+                Some(_) => service.proto.clone(),
+                None => protos
+                    .files
+                    .iter()
+                    .find(|f| f.proto.services.iter().any(|s| s.name == service.name))
+                    .map(|f| f.name.clone())
+                    .ok_or_else(|| {
+                        format!(
+                            "Service '{}' not found in descriptor set '{}'",
+                            service.name, path
+                        )
+                    })?,
+            };
+            if !protos.set_root(&root) {
+                return Err(format!(
+                    "Proto file '{}' not found in descriptor set '{}'",
+                    root, path
+                )
+                .into());
+            }
+            Ok(protos)
+        }
+        None => {
+            let protos = resolver.resolve(Path::new(&service.proto))?;
+            validate::validate(&protos)?;
+            Ok(protos)
         }
     }
+}
+
+/// Whether `protos` declares an extension of `google.protobuf.MethodOptions`
+/// carrying the custom option `option`: the extension itself, such as
+/// `acme.auth.required`, or a field of a message-typed one.
+fn declares_method_option(protos: &ProtoSet, option: &str) -> bool {
+    protos
+        .files
+        .iter()
+        .flat_map(|f| f.proto.all_extends())
+        .filter(|extend| extend.extendee.trim_start_matches('.') == "google.protobuf.MethodOptions")
+        .flat_map(|extend| extend.fields.iter().map(|field| extend.full_name(field)))
+        .any(|name| {
+            option
+                .strip_prefix(name.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+}
+
+/// Strips the parentheses and leading dot a custom option may be written
+/// with, e.g. `(.acme.auth.required)` becomes `acme.auth.required`.
+fn option_name(name: &str) -> &str {
+    let name = name.trim();
+    let name = name.strip_prefix('(').unwrap_or(name);
+    let name = name.strip_suffix(')').unwrap_or(name);
+    name.strip_prefix('.').unwrap_or(name)
+}
 
+/// Names a message or enum relative to the service's package, e.g.
+/// `Outer.Inner`. Types from other packages keep their fully-qualified name
+/// with a leading dot, e.g. `.common.v1.Money`.
+fn type_name(protos: &ProtoSet, full_name: &str) -> String {
+    let root = protos.root();
+    match protos.file_of(full_name) {
+        Some(file) if file.package != root.package => format!(".{}", full_name),
+        _ => root.relative_name(full_name).to_string(),
+    }
+}
+
+/// Synthesizes endpoints from the `google.api.http` annotations of `svc`,
+/// ordered by RPC name. Only the primary binding of each rule is exposed, with
+/// its path template left for [`route_path`]; a warning names any
+/// `additional_bindings` left out.
+fn annotated_endpoints(svc: &model::Service) -> Vec<Endpoint> {
+    let mut rpcs: Vec<_> = svc.methods.values().filter(|m| m.http.is_some()).collect();
+    rpcs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    rpcs.into_iter()
+        .filter_map(|rpc| {
+            let rule = rpc.http.as_ref()?;
+            for binding in &rule.additional_bindings {
+                eprintln!(
+                    "⚠️ Skipping additional binding {} {} of RPC '{}.{}': only {} {} is exposed",
+                    binding.method, binding.path, svc.name, rpc.name, rule.method, rule.path
+                );
+            }
+            Some(Endpoint {
+                rpc: rpc.name.clone(),
+                method: rule.method.clone(),
+                path: rule.path.clone(),
+                description: None,
+                request: Request {
+                    body: Some(rule.body.clone()),
+                    ..Default::default()
+                },
+                response: Default::default(),
+                auth: false,
+                client_streaming: false,
+                server_streaming: false,
+            })
+        })
+        .collect()
+}
+
+/// Works out where each request field of `endpoint` is read from: the path,
+/// the headers, the body, or the query string.
+fn bind_request(
+    endpoint: &mut Endpoint,
+    defaults: &Headers,
+    client_streaming: bool,
+) -> Result<(), String> {
+    let request = &mut endpoint.request;
+    request.path_params = path_params(&endpoint.path, &request.fields)?;
+    bind_headers(request, defaults, client_streaming)?;
+    if endpoint.auth {
+        // Already forwarded once checked.
+        request
+            .headers
+            .pass_through
+            .retain(|h| h != "authorization");
+    }
+
+    let body = match request.body.take() {
+        Some(body) => body,
+        None if !client_streaming && matches!(endpoint.method.as_str(), "GET" | "DELETE") => None,
+        None => Some("*".to_string()),
+    };
+    if client_streaming {
+        if !request.path_params.is_empty() {
+            return Err("Path parameters are not supported on client-streaming RPCs".into());
+        }
+        if body.as_deref() != Some("*") {
+            return Err("The body of a client-streaming RPC must be '*'".into());
+        }
+    }
+    if let Some(name) = body.as_deref().filter(|body| *body != "*") {
+        let field = request
+            .fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("Body '{}' is not a request field", name))?;
+        if field.kind != FieldKind::Message || field.repeated || field.key_type.is_some() {
+            return Err(format!("Body '{}' must be a singular message field", name));
+        }
+        if request.path_params.iter().any(|p| p == name) {
+            return Err(format!("Body '{}' is also a path parameter", name));
+        }
+    }
+
+    request.query_params = match body.as_deref() {
+        Some("*") => Vec::new(),
+        body => request
+            .fields
+            .iter()
+            .filter(|f| Some(f.name.as_str()) != body && !request.path_params.contains(&f.name))
+            .filter(|f| !request.headers.fields.iter().any(|h| h.field == f.name))
+            .filter(|f| f.kind != FieldKind::Message && f.key_type.is_none())
+            .filter(|f| f.proto_type != "bytes")
+            .map(|f| f.name.clone())
+            .collect(),
+    };
+    request.body = Some(body);
     Ok(())
 }
 
+/// Merges the service's `defaults` into the headers of `request`, then checks
+/// and normalizes them: header names are lowercased and every metadata
+/// mapping gets its key.
+fn bind_headers(
+    request: &mut Request,
+    defaults: &Headers,
+    client_streaming: bool,
+) -> Result<(), String> {
+    let headers = &mut request.headers;
+    for mapping in &defaults.fields {
+        let mapped = headers
+            .fields
+            .iter()
+            .any(|m| m.header.eq_ignore_ascii_case(&mapping.header));
+        let has_field = request.fields.iter().any(|f| f.name == mapping.field);
+        if !mapped && has_field && !client_streaming {
+            headers.fields.push(mapping.clone());
+        }
+    }
+    for mapping in &defaults.metadata {
+        let mapped = headers
+            .metadata
+            .iter()
+            .any(|m| m.header.eq_ignore_ascii_case(&mapping.header));
+        if !mapped {
+            headers.metadata.push(mapping.clone());
+        }
+    }
+    for header in &defaults.pass_through {
+        if !headers
+            .pass_through
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(header))
+        {
+            headers.pass_through.push(header.clone());
+        }
+    }
+
+    let mut bound: Vec<&str> = Vec::new();
+    for mapping in &mut headers.fields {
+        mapping.header = header_name(&mapping.header)?;
+        if client_streaming {
+            return Err("Header fields are not supported on client-streaming RPCs".into());
+        }
+        let field = request
+            .fields
+            .iter()
+            .find(|f| f.name == mapping.field)
+            .ok_or_else(|| {
+                format!(
+                    "Header '{}' maps to '{}', which is not a request field",
+                    mapping.header, mapping.field
+                )
+            })?;
+        if field.repeated
+            || field.key_type.is_some()
+            || field.kind == FieldKind::Message
+            || field.proto_type == "bytes"
+        {
+            return Err(format!(
+                "Header '{}' must map to a singular scalar or enum field, not '{}'",
+                mapping.header, mapping.field
+            ));
+        }
+        if request.path_params.contains(&field.name) {
+            return Err(format!(
+                "Field '{}' is bound from both the path and header '{}'",
+                field.name, mapping.header
+            ));
+        }
+        if bound.contains(&field.name.as_str()) {
+            return Err(format!("Field '{}' is bound from two headers", field.name));
+        }
+        bound.push(&field.name);
+    }
+    for mapping in &mut headers.metadata {
+        mapping.header = header_name(&mapping.header)?;
+        let key = mapping.key.as_deref().unwrap_or(&mapping.header);
+        mapping.key = Some(metadata_key(key)?);
+    }
+    for header in &mut headers.pass_through {
+        *header = metadata_key(&header_name(header)?)?;
+    }
+    Ok(())
+}
+
+/// Lowercases an HTTP header name, checking it is a valid token.
+fn header_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+    if !valid {
+        return Err(format!("Invalid header name '{}'", name));
+    }
+    Ok(name.to_ascii_lowercase())
+}
+
+/// Checks that `key` can carry a text value as gRPC metadata: lowercase
+/// letters, digits, `-`, `_` and `.`, outside the reserved `grpc-` prefix
+/// and the `-bin` suffix of binary metadata.
+fn metadata_key(key: &str) -> Result<String, String> {
+    let valid = !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-_.".contains(&b));
+    if !valid || key.starts_with("grpc-") || key.ends_with("-bin") {
+        return Err(format!("Invalid gRPC metadata key '{}'", key));
+    }
+    Ok(key.to_string())
+}
+
+/// Names the `{field}` and `{*field}` segments of `path`, checking that each
+/// is a singular scalar or enum field of the request, and a string when it
+/// matches several segments.
+fn path_params(path: &str, fields: &[Field]) -> Result<Vec<String>, String> {
+    let mut params: Vec<String> = Vec::new();
+    for segment in path.split('{').skip(1) {
+        let param = segment
+            .split_once('}')
+            .map(|(param, _)| param)
+            .ok_or_else(|| "Unclosed '{'".to_string())?;
+        let name = param.strip_prefix('*').unwrap_or(param);
+        if name.contains('.') {
+            return Err(format!(
+                "Path parameter '{{{}}}' names a nested field, which is not supported",
+                param
+            ));
+        }
+        let field = fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("Path parameter '{{{}}}' is not a request field", name))?;
+        if field.repeated || field.key_type.is_some() || field.kind == FieldKind::Message {
+            return Err(format!(
+                "Path parameter '{{{}}}' must be a singular scalar or enum field",
+                name
+            ));
+        }
+        if field.proto_type == "bytes" {
+            return Err(format!("Path parameter '{{{}}}' cannot be bytes", name));
+        }
+        if param.starts_with('*') && field.proto_type != "string" {
+            return Err(format!(
+                "Path parameter '{{{}}}' must be a string field",
+                param
+            ));
+        }
+        if params.iter().any(|p| p == name) {
+            return Err(format!("Path parameter '{{{}}}' is bound twice", name));
+        }
+        params.push(name.to_string());
+    }
+    Ok(params)
+}
+
+/// Reduces a `google.api.http` path template to a route, e.g.
+/// `/v1/books/{id=*}` becomes `/v1/books/{id}`. A variable matching several
+/// segments, such as `{name=shelves/*/books/*}` or `{name=**}`, becomes a
+/// `{*name}` wildcard, which routers only allow at the end of the path.
+pub fn route_path(template: &str) -> Result<String, String> {
+    let mut path = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        path.push_str(&rest[..start]);
+        let (variable, after) = rest[start + 1..]
+            .split_once('}')
+            .ok_or_else(|| "Unclosed '{'".to_string())?;
+        let (name, pattern) = variable.split_once('=').unwrap_or((variable, "*"));
+        if !pattern.contains('/') && !pattern.contains("**") {
+            path.push_str(&format!("{{{}}}", name));
+        } else if after.is_empty() {
+            path.push_str(&format!("{{*{}}}", name));
+        } else {
+            return Err(format!(
+                "Path parameter '{{{}}}' spans several segments, which is only supported at the end of the path",
+                variable
+            ));
+        }
+        rest = after;
+    }
+    path.push_str(rest);
+    Ok(path)
+}
+
+/// Builds config fields for `fields` declared in `message`, rewriting message
+/// and enum typed fields with [`type_name`] so nested and imported types
+/// survive. References that cannot be resolved are kept as written.
+fn resolve_fields(
+    protos: &ProtoSet,
+    message: &model::Message,
+    fields: &[model::Field],
+) -> Vec<Field> {
+    let file = protos.file_of(&message.full_name);
+
+    fields
+        .iter()
+        .map(|f| {
+            let (proto_type, kind) = if is_scalar(&f.field_type) {
+                (f.field_type.clone(), FieldKind::Scalar)
+            } else {
+                match protos.resolve(&message.full_name, &f.field_type) {
+                    Some(definition) => {
+                        let kind = match definition {
+                            Definition::Message(_) => FieldKind::Message,
+                            Definition::Enum(_) => FieldKind::Enum,
+                        };
+                        (type_name(protos, definition.full_name()), kind)
+                    }
+                    None => (f.field_type.clone(), FieldKind::Message),
+                }
+            };
+
+            Field {
+                name: f.name.clone(),
+                type_: proto_type.clone(),
+                proto_type,
+                http_type: String::new(),
+                optional: has_presence(file, message, f, kind),
+                kind,
+                repeated: f.repeated,
+                key_type: f.key_type.clone(),
+                json_name: f.json_name().map(str::to_string),
+                deprecated: f.deprecated(),
+                description: f.comments.doc(),
+            }
+        })
+        .collect()
+}
+
+/// Follows prost: singular fields track presence when the `field_presence`
+/// feature resolves to explicit, which is what `optional` and proto2 fields
+/// get, or when they hold a message, unless they are `required`.
+fn has_presence(
+    file: Option<&model::Proto>,
+    message: &model::Message,
+    field: &model::Field,
+    kind: FieldKind,
+) -> bool {
+    if field.repeated || field.key_type.is_some() {
+        return false;
+    }
+    let presence = match file {
+        Some(file) => file.field_presence(&message.full_name, field),
+        // Without its file, only an explicit `optional` is known to track presence.
+        None if field.label == Some(model::Label::Optional) => model::FieldPresence::Explicit,
+        None => model::FieldPresence::Implicit,
+    };
+    match presence {
+        model::FieldPresence::Explicit => true,
+        model::FieldPresence::Implicit => kind == FieldKind::Message,
+        model::FieldPresence::LegacyRequired => false,
+    }
+}
+
+/// Records `message` and every message or enum reachable from its fields, so
+/// the scaffolders can generate one HTTP type per proto type. Well-known types
+/// are left out; the generated projects ship their HTTP forms.
+fn collect_definitions(
+    protos: &ProtoSet,
+    message: &model::Message,
+    messages: &mut Vec<Message>,
+    enums: &mut Vec<Enum>,
+) {
+    if well_known::is_well_known(&message.full_name) {
+        return;
+    }
+
+    let name = type_name(protos, &message.full_name);
+    if messages.iter().any(|m| m.name == name) {
+        return;
+    }
+
+    let oneofs = message
+        .oneofs
+        .iter()
+        .map(|o| Oneof {
+            name: o.name.clone(),
+            type_: String::new(),
+            http_type: String::new(),
+            fields: resolve_fields(protos, message, &o.fields),
+            description: o.comments.doc(),
+        })
+        .collect();
+
+    messages.push(Message {
+        name,
+        type_: String::new(),
+        http_type: String::new(),
+        fields: resolve_fields(protos, message, &message.fields),
+        oneofs,
+        description: message.comments.doc(),
+    });
+
+    let members = message.oneofs.iter().flat_map(|o| &o.fields);
+    for field in message.fields.iter().chain(members) {
+        if is_scalar(&field.field_type) {
+            continue;
+        }
+        match protos.resolve(&message.full_name, &field.field_type) {
+            Some(Definition::Message(m)) => collect_definitions(protos, m, messages, enums),
+            Some(Definition::Enum(e)) if !well_known::is_well_known(&e.full_name) => {
+                let name = type_name(protos, &e.full_name);
+                if !enums.iter().any(|existing| existing.name == name) {
+                    enums.push(Enum {
+                        name,
+                        type_: String::new(),
+                        http_type: String::new(),
+                        values: enum_values(e),
+                        description: e.comments.doc(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Folds `allow_alias` duplicates into the first value declared with the same
+/// number, which is the name the canonical JSON mapping serializes.
+fn enum_values(e: &model::Enum) -> Vec<EnumValue> {
+    let mut values: Vec<EnumValue> = Vec::new();
+    for value in &e.values {
+        match values.iter_mut().find(|v| v.number == value.number) {
+            Some(existing) => existing.aliases.push(value.name.clone()),
+            None => values.push(EnumValue {
+                name: value.name.clone(),
+                number: value.number,
+                aliases: Vec::new(),
+                description: value.comments.doc(),
+            }),
+        }
+    }
+    values
+}
+
 fn map_field_types(config: &mut Config, framework: Framework) {
     let mapper = TypeMapperFactory { framework };
 
     for service in &mut config.spec.services {
         for endpoint in &mut service.endpoints {
+            endpoint.request.http_type = mapper.map_named(&endpoint.request.type_);
+            endpoint.request.type_ = mapper.map_type(&endpoint.request.type_);
+            endpoint.response.http_type = mapper.map_named(&endpoint.response.type_);
+            endpoint.response.type_ = mapper.map_type(&endpoint.response.type_);
             for field in &mut endpoint.request.fields {
-                field.type_ = mapper.map_type(&field.type_);
+                map_field(&mapper, field);
             }
             for field in &mut endpoint.response.fields {
-                field.type_ = mapper.map_type(&field.type_);
+                map_field(&mapper, field);
+            }
+        }
+
+        for message in &mut service.messages {
+            message.type_ = mapper.map_type(&message.name);
+            message.http_type = mapper.map_named(&message.name);
+            for field in &mut message.fields {
+                map_field(&mapper, field);
             }
+            for oneof in &mut message.oneofs {
+                // prost nests the oneof enum in the message's module, named
+                // after the group, e.g. `payment::Method`.
+                let name = format!("{}.{}", message.name, to_pascal_case(&oneof.name));
+                oneof.type_ = mapper.map_type(&name);
+                oneof.http_type = mapper.map_named(&name);
+                for field in &mut oneof.fields {
+                    field.http_type = mapper.map_element(field);
+                    field.type_ = mapper.map_variant(field);
+                }
+            }
+        }
+
+        for e in &mut service.enums {
+            e.type_ = mapper.map_type(&e.name);
+            e.http_type = mapper.map_named(&e.name);
+        }
+    }
+}
+
+fn map_field(mapper: &TypeMapperFactory, field: &mut Field) {
+    field.http_type = mapper.map_element(field);
+    field.type_ = mapper.map_field(field);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the config of one `Books` service declared in `proto`, written
+    /// under a directory named after `test`.
+    fn build(test: &str, proto: &str, endpoints: serde_json::Value) -> Config {
+        let dir = std::env::temp_dir().join(format!("havoc_{}_{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("books.proto");
+        fs::write(&path, proto).unwrap();
+        let content = serde_json::json!({
+            "metadata": { "name": "books", "version": "1" },
+            "spec": {
+                "host": "127.0.0.1",
+                "port": 3000,
+                "services": [{
+                    "name": "Books",
+                    "proto": path,
+                    "url": "http://localhost:50051",
+                    "endpoints": endpoints,
+                }],
+            },
+        });
+        let config = ParserFactory::from_content("havoc.json", content.to_string())
+            .and_then(|factory| factory.build());
+        fs::remove_dir_all(dir).unwrap();
+        config.unwrap()
+    }
+
+    fn field(name: &str, proto_type: &str, kind: FieldKind) -> Field {
+        Field {
+            name: name.to_string(),
+            type_: proto_type.to_string(),
+            proto_type: proto_type.to_string(),
+            http_type: String::new(),
+            kind,
+            repeated: false,
+            optional: false,
+            key_type: None,
+            json_name: None,
+            deprecated: false,
+            description: None,
         }
     }
+
+    fn book_fields() -> Vec<Field> {
+        vec![
+            field("shelf_id", "int64", FieldKind::Scalar),
+            field("id", "string", FieldKind::Scalar),
+            field("genre", "Genre", FieldKind::Enum),
+            field("book", "Book", FieldKind::Message),
+            field("cover", "bytes", FieldKind::Scalar),
+        ]
+    }
+
+    fn endpoint(method: &str, path: &str, body: Option<Option<&str>>) -> Endpoint {
+        Endpoint {
+            rpc: "UpdateBook".to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            description: None,
+            request: Request {
+                fields: book_fields(),
+                body: body.map(|body| body.map(str::to_string)),
+                ..Default::default()
+            },
+            response: Default::default(),
+            auth: false,
+            client_streaming: false,
+            server_streaming: false,
+        }
+    }
+
+    /// Binds `endpoint`, returning its body and query parameters.
+    fn bind(mut endpoint: Endpoint) -> Result<(Option<String>, Vec<String>), String> {
+        let client_streaming = endpoint.client_streaming;
+        bind_request(&mut endpoint, &Headers::default(), client_streaming)?;
+        let request = endpoint.request;
+        Ok((request.body.unwrap(), request.query_params))
+    }
+
+    #[test]
+    fn test_route_path() {
+        assert_eq!(route_path("/v1/books/{id}").unwrap(), "/v1/books/{id}");
+        assert_eq!(route_path("/v1/{name=books/*}").unwrap(), "/v1/{*name}");
+        assert_eq!(
+            route_path("/v1/shelves/{shelf_id=*}/books").unwrap(),
+            "/v1/shelves/{shelf_id}/books"
+        );
+        assert_eq!(
+            route_path("/v1/{name=shelves/*/books/*}").unwrap(),
+            "/v1/{*name}"
+        );
+        assert_eq!(route_path("/files/{path=**}").unwrap(), "/files/{*path}");
+        assert_eq!(route_path("/files/{*path}").unwrap(), "/files/{*path}");
+        assert_eq!(
+            route_path("/v1/{parent=shelves/*}/books").unwrap_err(),
+            "Path parameter '{parent=shelves/*}' spans several segments, which is only supported at the end of the path"
+        );
+        assert_eq!(route_path("/v1/{id").unwrap_err(), "Unclosed '{'");
+    }
+
+    #[test]
+    fn test_path_params() {
+        let fields = book_fields();
+        assert_eq!(
+            path_params("/shelves/{shelf_id}/books/{id}", &fields).unwrap(),
+            ["shelf_id", "id"]
+        );
+        assert_eq!(path_params("/books/{genre}", &fields).unwrap(), ["genre"]);
+        assert_eq!(path_params("/files/{*id}", &fields).unwrap(), ["id"]);
+        assert!(path_params("/books", &fields).unwrap().is_empty());
+
+        let error = |path: &str| path_params(path, &fields).unwrap_err();
+        assert_eq!(
+            error("/books/{book.id}"),
+            "Path parameter '{book.id}' names a nested field, which is not supported"
+        );
+        assert_eq!(
+            error("/books/{title}"),
+            "Path parameter '{title}' is not a request field"
+        );
+        assert_eq!(
+            error("/books/{book}"),
+            "Path parameter '{book}' must be a singular scalar or enum field"
+        );
+        assert_eq!(
+            error("/books/{cover}"),
+            "Path parameter '{cover}' cannot be bytes"
+        );
+        assert_eq!(
+            error("/shelves/{*shelf_id}"),
+            "Path parameter '{*shelf_id}' must be a string field"
+        );
+        assert_eq!(
+            error("/books/{id}/{id}"),
+            "Path parameter '{id}' is bound twice"
+        );
+    }
+
+    #[test]
+    fn test_bind_request_body() {
+        let all = bind(endpoint("POST", "/books/{id}", None)).unwrap();
+        assert_eq!(all, (Some("*".to_string()), vec![]));
+        let all = bind(endpoint("PUT", "/books/{id}", Some(Some("*")))).unwrap();
+        assert_eq!(all, (Some("*".to_string()), vec![]));
+
+        let query = vec!["shelf_id".to_string(), "genre".to_string()];
+        let field = bind(endpoint("PATCH", "/books/{id}", Some(Some("book")))).unwrap();
+        assert_eq!(field, (Some("book".to_string()), query.clone()));
+        let null = bind(endpoint("POST", "/books/{id}", Some(None))).unwrap();
+        assert_eq!(null, (None, query.clone()));
+
+        for method in ["GET", "DELETE"] {
+            let fallback = bind(endpoint(method, "/books/{id}", None)).unwrap();
+            assert_eq!(fallback, (None, query.clone()));
+        }
+        let get = bind(endpoint("GET", "/books", Some(Some("book")))).unwrap();
+        assert_eq!(get.1, ["shelf_id", "id", "genre"]);
+    }
+
+    #[test]
+    fn test_bind_request_errors() {
+        let error = |endpoint| bind(endpoint).unwrap_err();
+        assert_eq!(
+            error(endpoint("POST", "/books", Some(Some("title")))),
+            "Body 'title' is not a request field"
+        );
+        assert_eq!(
+            error(endpoint("POST", "/books", Some(Some("genre")))),
+            "Body 'genre' must be a singular message field"
+        );
+
+        let mut streaming = endpoint("POST", "/books/{id}", None);
+        streaming.client_streaming = true;
+        assert_eq!(
+            error(streaming),
+            "Path parameters are not supported on client-streaming RPCs"
+        );
+        let mut streaming = endpoint("POST", "/books", Some(Some("book")));
+        streaming.client_streaming = true;
+        assert_eq!(
+            error(streaming),
+            "The body of a client-streaming RPC must be '*'"
+        );
+    }
+
+    fn headers(value: serde_json::Value) -> Headers {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_header_name() {
+        assert_eq!(header_name("X-Tenant-Id").unwrap(), "x-tenant-id");
+        assert_eq!(header_name("x_b3.traceid").unwrap(), "x_b3.traceid");
+        for name in ["", "X Tenant", "x-tenant:", "x-tënant"] {
+            let error = format!("Invalid header name '{}'", name);
+            assert_eq!(header_name(name).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_metadata_key() {
+        assert_eq!(metadata_key("client-version").unwrap(), "client-version");
+        assert_eq!(metadata_key("x_b3.traceid").unwrap(), "x_b3.traceid");
+        for key in ["", "Client-Version", "grpc-timeout", "trace-bin", "x!y"] {
+            let error = format!("Invalid gRPC metadata key '{}'", key);
+            assert_eq!(metadata_key(key).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_bind_headers() {
+        let defaults = headers(serde_json::json!({
+            "fields": [
+                { "header": "X-Shelf", "field": "shelf_id" },
+                { "header": "X-Tenant", "field": "tenant_id" },
+            ],
+            "metadata": [{ "header": "Traceparent" }],
+            "passThrough": ["Authorization", "X-Request-Id"],
+        }));
+        let mut endpoint = endpoint("GET", "/books/{id}", None);
+        endpoint.request.headers = headers(serde_json::json!({
+            "fields": [{ "header": "x-shelf", "field": "genre" }],
+            "metadata": [{ "header": "X-Client-Version", "key": "client-version" }],
+            "passThrough": ["x-request-id"],
+        }));
+        bind_request(&mut endpoint, &defaults, false).unwrap();
+
+        let bound = &endpoint.request.headers;
+        let fields: Vec<_> = bound
+            .fields
+            .iter()
+            .map(|m| (m.header.as_str(), m.field.as_str()))
+            .collect();
+        assert_eq!(fields, [("x-shelf", "genre")]);
+        let metadata: Vec<_> = bound
+            .metadata
+            .iter()
+            .map(|m| (m.header.as_str(), m.key.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            metadata,
+            [
+                ("x-client-version", "client-version"),
+                ("traceparent", "traceparent")
+            ]
+        );
+        assert_eq!(bound.pass_through, ["x-request-id", "authorization"]);
+        // Header fields are not read from the query string.
+        assert_eq!(endpoint.request.query_params, ["shelf_id"]);
+    }
+
+    #[test]
+    fn test_bind_headers_skips_authorization_when_checked() {
+        let defaults = headers(serde_json::json!({
+            "passThrough": ["Authorization", "X-Request-Id"],
+        }));
+        let mut endpoint = endpoint("GET", "/books/{id}", None);
+        endpoint.auth = true;
+        bind_request(&mut endpoint, &defaults, false).unwrap();
+        assert_eq!(endpoint.request.headers.pass_through, ["x-request-id"]);
+    }
+
+    #[test]
+    fn test_bind_headers_errors() {
+        let error = |fields: serde_json::Value, path: &str, client_streaming: bool| {
+            let mut endpoint = endpoint("POST", path, None);
+            endpoint.request.headers = headers(serde_json::json!({ "fields": fields }));
+            bind_request(&mut endpoint, &Headers::default(), client_streaming).unwrap_err()
+        };
+
+        let field =
+            |header: &str, field: &str| serde_json::json!([{ "header": header, "field": field }]);
+        assert_eq!(
+            error(field("X-Title", "title"), "/books", false),
+            "Header 'x-title' maps to 'title', which is not a request field"
+        );
+        for name in ["book", "cover"] {
+            assert_eq!(
+                error(field("X-Value", name), "/books", false),
+                format!(
+                    "Header 'x-value' must map to a singular scalar or enum field, not '{}'",
+                    name
+                )
+            );
+        }
+        assert_eq!(
+            error(field("X-Id", "id"), "/books/{id}", false),
+            "Field 'id' is bound from both the path and header 'x-id'"
+        );
+        let twice = serde_json::json!([
+            { "header": "X-Shelf", "field": "shelf_id" },
+            { "header": "X-Shelf-Id", "field": "shelf_id" },
+        ]);
+        assert_eq!(
+            error(twice, "/books", false),
+            "Field 'shelf_id' is bound from two headers"
+        );
+        assert_eq!(
+            error(field("X-Shelf", "shelf_id"), "/books", true),
+            "Header fields are not supported on client-streaming RPCs"
+        );
+        assert_eq!(
+            error(field("X Shelf", "shelf_id"), "/books", false),
+            "Invalid header name 'X Shelf'"
+        );
+    }
+
+    const ANNOTATED: &str = r#"
+        syntax = "proto3";
+        package books;
+        service Books {
+            rpc GetBook (GetBookRequest) returns (Book) {
+                option (google.api.http) = { get: "/v1/{name=shelves/*/books/*}" };
+            }
+            rpc CreateBook (CreateBookRequest) returns (Book) {
+                option (google.api.http) = {
+                    post: "/v1/shelves/{shelf_id}/books"
+                    body: "book"
+                    additional_bindings { put: "/v1/books" body: "*" }
+                };
+            }
+            rpc Ping (Book) returns (Book);
+        }
+        message GetBookRequest { string name = 1; }
+        message CreateBookRequest { string shelf_id = 1; Book book = 2; bool notify = 3; }
+        message Book { string name = 1; }
+    "#;
+
+    #[test]
+    fn test_populate_annotated_endpoints() {
+        let config = build("annotated", ANNOTATED, serde_json::json!([]));
+        let endpoints = &config.spec.services[0].endpoints;
+        let routes: Vec<_> = endpoints
+            .iter()
+            .map(|e| format!("{} {} {}", e.rpc, e.method, e.path))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                "CreateBook POST /v1/shelves/{shelf_id}/books",
+                "GetBook GET /v1/{*name}",
+            ]
+        );
+        let create = &endpoints[0].request;
+        assert_eq!(create.body, Some(Some("book".to_string())));
+        assert_eq!(create.path_params, ["shelf_id"]);
+        assert_eq!(create.query_params, ["notify"]);
+        assert_eq!(endpoints[1].request.path_params, ["name"]);
+    }
+
+    #[test]
+    fn test_populate_configured_endpoints_override_annotations() {
+        let endpoints = serde_json::json!([
+            { "rpc": "GetBook", "method": "post", "path": "/books/lookup" },
+            { "rpc": "Ping", "method": "get", "path": "/ping" },
+        ]);
+        let config = build("configured", ANNOTATED, endpoints);
+        let routes: Vec<_> = config.spec.services[0]
+            .endpoints
+            .iter()
+            .map(|e| format!("{} {} {} {:?}", e.rpc, e.method, e.path, e.request.body))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                "GetBook POST /books/lookup Some(Some(\"*\"))",
+                "Ping GET /ping Some(None)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_path_order() {
+        let dir = std::env::temp_dir().join(format!("havoc_includes_{}", std::process::id()));
+        for root in ["cli", "config", "own"] {
+            fs::create_dir_all(dir.join(root)).unwrap();
+            let common = format!(
+                "syntax = \"proto3\"; package common; message Money {{ int64 {} = 1; }}",
+                root
+            );
+            fs::write(dir.join(root).join("common.proto"), common).unwrap();
+        }
+        let proto = dir.join("own").join("books.proto");
+        fs::write(
+            &proto,
+            r#"
+            syntax = "proto3";
+            import "common.proto";
+            service Books { rpc GetPrice (common.Money) returns (common.Money); }
+        "#,
+        )
+        .unwrap();
+
+        let resolved_from = |cli: &[&str], config: &[&str]| {
+            let paths = |roots: &[&str]| -> Vec<_> {
+                roots
+                    .iter()
+                    .map(|root| dir.join(root).display().to_string())
+                    .collect()
+            };
+            let content = serde_json::json!({
+                "metadata": { "name": "books", "version": "1" },
+                "spec": {
+                    "host": "127.0.0.1",
+                    "port": 3000,
+                    "protoPaths": paths(config),
+                    "services": [{
+                        "name": "Books",
+                        "proto": proto,
+                        "url": "http://localhost:50051",
+                        "endpoints": [{ "rpc": "GetPrice", "method": "post", "path": "/price" }],
+                    }],
+                },
+            });
+            let config = ParserFactory::from_content("havoc.json", content.to_string())
+                .unwrap()
+                .with_proto_paths(paths(cli))
+                .build()
+                .unwrap();
+            let request = &config.spec.services[0].endpoints[0].request;
+            request.fields[0].name.clone()
+        };
+
+        assert_eq!(resolved_from(&["cli"], &["config"]), "cli");
+        assert_eq!(resolved_from(&[], &["config"]), "config");
+        assert_eq!(resolved_from(&[], &[]), "own");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Encodes `bytes` as the length-delimited field `tag`, enough for the
+    /// small descriptor sets built here.
+    fn nested(tag: u8, bytes: &[u8]) -> Vec<u8> {
+        [&[tag << 3 | 2, bytes.len() as u8][..], bytes].concat()
+    }
+
+    /// A `FileDescriptorSet` of files named `name` in package `books`, each
+    /// declaring the service given with it.
+    fn descriptor_set(files: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let file = |(name, service): &(&str, Option<&str>)| {
+            let service = service.map(|service| nested(6, &nested(1, service.as_bytes())));
+            let file = [
+                nested(1, name.as_bytes()),
+                nested(2, b"books"),
+                service.unwrap_or_default(),
+            ];
+            nested(1, &file.concat())
+        };
+        files.iter().flat_map(file).collect()
+    }
+
+    fn service(value: serde_json::Value) -> Service {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_load_protos_root() {
+        let path = std::env::temp_dir().join(format!("havoc_root_{}.pb", std::process::id()));
+        let set = descriptor_set(&[
+            ("common.proto", None),
+            ("books.proto", Some("Books")),
+            ("shelves.proto", Some("Shelves")),
+        ]);
+        fs::write(&path, set).unwrap();
+        let path = path.display().to_string();
+        let resolver = Resolver::new(Vec::new());
+        let root = |value: serde_json::Value| {
+            load_protos(&resolver, &service(value)).map(|protos| protos.files[0].name.clone())
+        };
+        let url = "http://localhost:50051";
+
+        let found = root(serde_json::json!({ "name": "Shelves", "proto": path, "url": url }));
+        assert_eq!(found.unwrap(), "shelves.proto");
+        let named = root(serde_json::json!({
+            "name": "Books",
+            "proto": "books.proto",
+            "protoDescriptor": path,
+            "url": url,
+        }));
+        assert_eq!(named.unwrap(), "books.proto");
+
+        let missing = root(serde_json::json!({ "name": "Authors", "proto": path, "url": url }));
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            format!("Service 'Authors' not found in descriptor set '{}'", path)
+        );
+        let missing = root(serde_json::json!({
+            "name": "Books",
+            "proto": "authors.proto",
+            "protoDescriptor": path,
+            "url": url,
+        }));
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            format!(
+                "Proto file 'authors.proto' not found in descriptor set '{}'",
+                path
+            )
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_auth_option_requires_declaration_in_descriptor_set() {
+        let path = std::env::temp_dir().join(format!("havoc_auth_{}.pb", std::process::id()));
+        fs::write(&path, descriptor_set(&[("books.proto", Some("Books"))])).unwrap();
+        let content = serde_json::json!({
+            "metadata": { "name": "books", "version": "1" },
+            "spec": {
+                "host": "127.0.0.1",
+                "port": 3000,
+                "authOption": "(acme.auth.required)",
+                "services": [{ "name": "Books", "proto": path, "url": "http://localhost:50051" }],
+            },
+        });
+        let error = ParserFactory::from_content("havoc.json", content.to_string())
+            .and_then(|factory| factory.build())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Auth option 'acme.auth.required' is not declared in descriptor set '{}'; build it with --include_imports",
+                path.display()
+            )
+        );
+        fs::remove_file(path).unwrap();
+    }
 }
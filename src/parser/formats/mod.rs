@@ -18,4 +18,3 @@ impl FromStr for FileFormat {
         }
     }
 }
-
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -27,17 +30,83 @@ pub struct Metadata {
 pub struct Spec {
     pub host: String,
     pub port: u16,
+    /// Include paths searched for proto imports, like `protoc -I`.
+    #[serde(rename = "protoPaths", default)]
+    pub proto_paths: Vec<String>,
+    /// Custom RPC option marking endpoints that require an `Authorization`
+    /// header, e.g. `acme.auth.required`.
+    #[serde(rename = "authOption", default)]
+    pub auth_option: Option<String>,
     pub services: Vec<Service>,
 }
 
+impl Spec {
+    /// Include paths for proto imports: `protoPaths` first, then the
+    /// directory of every service's proto file.
+    pub fn include_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.proto_paths.iter().map(PathBuf::from).collect();
+        for service in self.services.iter().filter(|s| s.descriptor().is_none()) {
+            let dir = match PathBuf::from(&service.proto).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            if !paths.contains(&dir) {
+                paths.push(dir);
+            }
+        }
+        paths
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Service {
     pub name: String,
     pub proto: String,
+    /// A compiled `FileDescriptorSet` to read instead of `.proto` sources;
+    /// `proto` then names the file in the set that declares the service.
+    #[serde(rename = "protoDescriptor", default)]
+    pub proto_descriptor: Option<String>,
     pub url: String,
+    /// Documentation for the generated code, defaulting to the service's
+    /// proto doc comment.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// When empty, endpoints are taken from the RPCs' `google.api.http`
+    /// annotations instead.
+    #[serde(default)]
     pub endpoints: Vec<Endpoint>,
     #[serde(default)]
     pub options: HashMap<String, String>,
+    /// Headers read by every endpoint, unless mapped by the endpoint itself.
+    /// Field mappings only apply to requests that have the field, and not to
+    /// client-streaming RPCs.
+    #[serde(default)]
+    pub headers: Headers,
+    /// Package of the proto file declaring the service.
+    #[serde(default, skip_deserializing)]
+    pub package: String,
+    #[serde(default, skip_deserializing)]
+    pub messages: Vec<Message>,
+    #[serde(default, skip_deserializing)]
+    pub enums: Vec<Enum>,
+}
+
+impl Service {
+    /// The descriptor set the service is loaded from: `protoDescriptor`, or
+    /// `proto` itself when it has a descriptor extension (`.pb`, `.binpb`,
+    /// `.desc`).
+    pub fn descriptor(&self) -> Option<&str> {
+        if let Some(descriptor) = &self.proto_descriptor {
+            return Some(descriptor);
+        }
+        let extension = Path::new(&self.proto).extension()?.to_str()?;
+        matches!(extension, "pb" | "binpb" | "desc").then_some(self.proto.as_str())
+    }
+
+    /// The file that must exist on disk for the service to load.
+    pub fn source(&self) -> &str {
+        self.descriptor().unwrap_or(&self.proto)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -45,54 +114,187 @@ pub struct Endpoint {
     pub rpc: String,
     pub method: String,
     pub path: String,
+    /// Defaults to the RPC's proto doc comment.
+    #[serde(default)]
+    pub description: Option<String>,
     #[serde(default)]
     pub request: Request,
     #[serde(default)]
     pub response: Response,
+    /// Requires an `Authorization` header, forwarded to the gRPC call. Also
+    /// set when the RPC has the spec's `authOption` set to `true`.
+    #[serde(default)]
+    pub auth: bool,
+    #[serde(rename = "clientStreaming", default, skip_deserializing)]
+    pub client_streaming: bool,
+    #[serde(rename = "serverStreaming", default, skip_deserializing)]
+    pub server_streaming: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Request {
     #[serde(rename = "type", default)]
     pub type_: String,
+    #[serde(rename = "httpType", default, skip_deserializing)]
+    pub http_type: String,
     #[serde(default)]
     pub fields: Vec<Field>,
+    /// What the HTTP body holds, as in `google.api.http`: `*` for the whole
+    /// message, the name of a message field, or `null` for nothing, binding
+    /// the remaining fields from the query string. Unset, it is `*`, or
+    /// `null` for `GET` and `DELETE`.
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub body: Option<Option<String>>,
+    /// Request fields bound from the `{field}` segments of the endpoint path.
+    #[serde(rename = "pathParams", default, skip_deserializing)]
+    pub path_params: Vec<String>,
+    /// Request fields bound from the query string, when the body does not
+    /// hold the whole message.
+    #[serde(rename = "queryParams", default, skip_deserializing)]
+    pub query_params: Vec<String>,
+    /// Headers read into the request fields or forwarded as gRPC metadata,
+    /// merged with the service's `headers`.
+    #[serde(default)]
+    pub headers: Headers,
 }
 
-impl Default for Request {
-    fn default() -> Self {
-        Request {
-            type_: String::new(),
-            fields: Vec::new(),
-        }
-    }
+/// How incoming HTTP headers reach a gRPC call. Header names are matched
+/// case-insensitively and kept lowercased.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct Headers {
+    #[serde(default)]
+    pub fields: Vec<HeaderField>,
+    #[serde(default)]
+    pub metadata: Vec<HeaderMetadata>,
+    /// Headers forwarded as gRPC metadata of the same name.
+    #[serde(rename = "passThrough", default)]
+    pub pass_through: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A header setting a singular scalar or enum field of the request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeaderField {
+    pub header: String,
+    pub field: String,
+}
+
+/// A header forwarded as gRPC metadata under `key`, defaulting to the header
+/// name.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeaderMetadata {
+    pub header: String,
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing value (`None`).
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Response {
     #[serde(rename = "type", default)]
     pub type_: String,
+    #[serde(rename = "httpType", default, skip_deserializing)]
+    pub http_type: String,
     #[serde(default)]
     pub fields: Vec<Field>,
     #[serde(default)]
     pub cookies: Vec<Cookie>,
 }
 
-impl Default for Response {
-    fn default() -> Self {
-        Response {
-            type_: String::new(),
-            fields: Vec::new(),
-            cookies: Vec::new(),
-        }
-    }
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(rename = "protoType", default)]
+    pub proto_type: String,
+    /// Element type in the generated HTTP layer, see `type` for the full type.
+    #[serde(rename = "httpType", default, skip_deserializing)]
+    pub http_type: String,
+    #[serde(default)]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub repeated: bool,
+    /// Whether presence is tracked, making the field nullable.
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(rename = "keyType", default)]
+    pub key_type: Option<String>,
+    /// JSON name from the field's `json_name` option, when it overrides `name`.
+    #[serde(rename = "jsonName", default)]
+    pub json_name: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    #[default]
+    Scalar,
+    Message,
+    Enum,
+}
+
+/// A proto message reachable from a service's endpoints, mirrored as a DTO in
+/// the generated HTTP layer.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Field {
+pub struct Message {
     pub name: String,
     #[serde(rename = "type")]
     pub type_: String,
+    #[serde(rename = "httpType")]
+    pub http_type: String,
+    pub fields: Vec<Field>,
+    #[serde(default)]
+    pub oneofs: Vec<Oneof>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A `oneof` group, generated as a tagged union holding exactly one member.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Oneof {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(rename = "httpType")]
+    pub http_type: String,
+    pub fields: Vec<Field>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A proto enum reachable from a service's endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Enum {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(rename = "httpType")]
+    pub http_type: String,
+    pub values: Vec<EnumValue>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EnumValue {
+    pub name: String,
+    pub number: i32,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -114,3 +316,33 @@ pub struct CookieOptions {
     pub domain: Option<String>,
     pub partitioned: Option<bool>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_descriptor() {
+        let service = |proto: &str, descriptor: Option<&str>| Service {
+            proto_descriptor: descriptor.map(str::to_string),
+            ..serde_json::from_value(serde_json::json!({
+                "name": "Books",
+                "proto": proto,
+                "url": "http://localhost:50051",
+            }))
+            .unwrap()
+        };
+
+        for proto in ["books.pb", "out/books.binpb", "books.desc"] {
+            assert_eq!(service(proto, None).descriptor(), Some(proto));
+            assert_eq!(service(proto, None).source(), proto);
+        }
+        assert_eq!(service("books.proto", None).descriptor(), None);
+        assert_eq!(service("books.proto", None).source(), "books.proto");
+        assert_eq!(service("pb", None).descriptor(), None);
+
+        let named = service("books/v1/books.proto", Some("books.binpb"));
+        assert_eq!(named.descriptor(), Some("books.binpb"));
+        assert_eq!(named.source(), "books.binpb");
+    }
+}
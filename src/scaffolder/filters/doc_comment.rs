@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use tera::Value;
+
+use super::to_camel_case;
+
+/// Renders a description as a doc comment: `style` is `rust` (`///`, the
+/// default) or `javadoc`. Lines after the first are indented by `indent`
+/// spaces, and the comment ends with a newline and that indentation so the
+/// declaration it documents lines up. Empty descriptions render nothing.
+///
+/// For Javadoc, `params` lists items with a `name` and `description`, such as
+/// record components, documented as `@param` tags.
+pub fn doc_comment_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let text = match value {
+        Value::Null => "",
+        value => value.as_str().ok_or("Expected a string")?,
+    };
+    let style = args.get("style").and_then(Value::as_str).unwrap_or("rust");
+    let indent = args.get("indent").and_then(Value::as_u64).unwrap_or(0) as usize;
+
+    let mut lines: Vec<String> = text.trim().lines().map(str::to_string).collect();
+    if style == "javadoc" {
+        let params = args.get("params").and_then(Value::as_array);
+        let tags: Vec<String> = params
+            .into_iter()
+            .flatten()
+            .filter_map(|param| {
+                let name = param.get("name")?.as_str()?;
+                let description = param.get("description")?.as_str()?;
+                Some(format!(
+                    "@param {} {}",
+                    to_camel_case(name),
+                    description.trim()
+                ))
+            })
+            .collect();
+        if !lines.is_empty() && !tags.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(tags);
+    }
+
+    Ok(Value::String(to_doc_comment(&lines, style, indent)))
+}
+
+pub fn to_doc_comment(lines: &[String], style: &str, indent: usize) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    let newline = format!("\n{}", " ".repeat(indent));
+    let text = lines.join("\n");
+    let lines = text.lines().map(str::trim_end);
+    let comment: Vec<String> = match style {
+        "javadoc" => std::iter::once("/**".to_string())
+            .chain(lines.map(|line| {
+                // `*/` would end the comment and `\u` is read as a unicode escape.
+                let line = line.replace("*/", "*&#47;").replace("\\u", "\\\\u");
+                format!(" * {}", line).trim_end().to_string()
+            }))
+            .chain(std::iter::once(" */".to_string()))
+            .collect(),
+        _ => lines
+            .map(|line| format!("/// {}", line).trim_end().to_string())
+            .collect(),
+    };
+    comment.join(&newline) + &newline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(text: &str, args: Value) -> String {
+        let args: HashMap<String, Value> = serde_json::from_value(args).unwrap();
+        let value = doc_comment_filter(&json!(text), &args).unwrap();
+        value.as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_rust_doc_comment() {
+        let text = " Looks up a \"book\".\n\nEnds with */ here. \n";
+        assert_eq!(
+            render(text, json!({ "indent": 4 })),
+            "/// Looks up a \"book\".\n    ///\n    /// Ends with */ here.\n    "
+        );
+        assert_eq!(render("", json!({})), "");
+        let null = doc_comment_filter(&Value::Null, &HashMap::new()).unwrap();
+        assert_eq!(null, json!(""));
+    }
+
+    #[test]
+    fn test_javadoc_comment_escapes() {
+        let text = "Matches /* and */ in \"quotes\".\nUses \\u0041.";
+        assert_eq!(
+            render(text, json!({ "style": "javadoc" })),
+            "/**\n * Matches /* and *&#47; in \"quotes\".\n * Uses \\\\u0041.\n */\n"
+        );
+    }
+
+    #[test]
+    fn test_javadoc_params() {
+        let args = json!({
+            "style": "javadoc",
+            "indent": 2,
+            "params": [
+                { "name": "page_size", "description": " Max results, ends */ " },
+                { "name": "skipped" },
+            ],
+        });
+        assert_eq!(
+            render("A page.", args),
+            "/**\n   * A page.\n   *\n   * @param pageSize Max results, ends *&#47;\n   */\n  "
+        );
+        assert_eq!(render("", json!({ "style": "javadoc", "params": [] })), "");
+    }
+}
@@ -1,9 +1,13 @@
-mod snake_case;
 mod camel_case;
 mod capitalize;
+mod doc_comment;
 mod lower_case;
+mod pascal_case;
+mod snake_case;
 
-pub use snake_case::*;
 pub use camel_case::*;
 pub use capitalize::*;
+pub use doc_comment::*;
 pub use lower_case::*;
+pub use pascal_case::*;
+pub use snake_case::*;
@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+use tera::Value;
+
+pub fn pascal_case_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let name = value.as_str().ok_or("Expected a string")?;
+    Ok(Value::String(to_pascal_case(name)))
+}
+
+pub fn to_pascal_case(name: &str) -> String {
+    let mut pascal = String::new();
+
+    for part in name.split('_').filter(|p| !p.is_empty()) {
+        let part = if part.chars().any(|c| c.is_lowercase()) {
+            part.to_string()
+        } else {
+            part.to_lowercase()
+        };
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            pascal.push(first.to_ascii_uppercase());
+            pascal.push_str(chars.as_str());
+        }
+    }
+
+    pascal
+}
@@ -18,6 +18,20 @@ pub struct AxumScaffolder;
 
 impl Scaffolder for AxumScaffolder {
     fn scaffold(config: &Config, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // Services are generated as `http::<name>`, next to the helpers.
+        if let Some(service) = config
+            .spec
+            .services
+            .iter()
+            .find(|s| filters::to_snake_case(&s.name) == "support")
+        {
+            return Err(format!(
+                "Service '{}' collides with the generated http::support module; rename it.",
+                service.name
+            )
+            .into());
+        }
+
         if !output.exists() {
             fs::create_dir_all(output)?;
         }
@@ -32,31 +46,58 @@ impl Scaffolder for AxumScaffolder {
             fs::create_dir_all(&gen_dir)?;
         }
 
+        let mut protos = Vec::new();
+        let mut descriptors = Vec::new();
         for service in &config.spec.services {
-            let proto_path = PathBuf::from(&service.proto);
+            let proto_path = PathBuf::from(service.source());
             if !proto_path.exists() {
-                return Err(format!("Proto file {} does not exist.", service.proto).into());
+                return Err(format!("Proto file {} does not exist.", service.source()).into());
             }
+            let paths = match service.descriptor() {
+                Some(_) => &mut descriptors,
+                None => &mut protos,
+            };
+            if !paths.contains(&proto_path) {
+                paths.push(proto_path);
+            }
+        }
 
-            let proto_dir = proto_path
-                .parent()
-                .ok_or("Could not resolve parent directory of proto")?;
-
-            tonic_build::configure()
-                .build_server(false)
-                .build_client(true)
-                .out_dir(&gen_dir)
-                .compile_protos(&[proto_path.clone()], &[proto_dir])?;
+        // Sources and compiled sets are merged into one descriptor set, so
+        // shared imports are generated once.
+        let mut fds = if protos.is_empty() {
+            tonic_build::FileDescriptorSet::default()
+        } else {
+            tonic_build::Config::new().load_fds(&protos, &config.spec.include_paths())?
+        };
+        for path in &descriptors {
+            for file in proto_def::descriptor::read(path)?.file {
+                if !fds.file.iter().any(|f| f.name == file.name) {
+                    fds.file.push(file);
+                }
+            }
         }
 
-        let mut tera = Tera::default();
-        tera.register_filter("snake_case", filters::snake_case_filter);
-        render_templates_recursively(&mut tera, &AXUM_TEMPLATES, output, config)?;
+        // `mod.rs` nests the output by proto package.
+        tonic_build::configure()
+            .build_server(false)
+            .build_client(true)
+            .out_dir(&gen_dir)
+            .include_file("mod.rs")
+            .compile_fds(fds)?;
 
-        Ok(())
+        render(config, output)
     }
 }
 
+/// Renders the templates for `config` into `output`.
+fn render(config: &Config, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tera = Tera::default();
+    tera.register_filter("snake_case", filters::snake_case_filter);
+    tera.register_filter("pascal_case", filters::pascal_case_filter);
+    tera.register_filter("doc_comment", filters::doc_comment_filter);
+    render_templates_recursively(&mut tera, &AXUM_TEMPLATES, output, config)
+}
+
 fn render_templates_recursively(
     tera: &mut Tera,
     dir: &Dir,
@@ -138,3 +179,53 @@ fn render_templates_recursively(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::factory::ParserFactory;
+
+    fn gateway() -> Config {
+        ParserFactory::new("examples/gateway.yaml")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render_gateway_example() {
+        let output = std::env::temp_dir().join(format!("havoc_axum_{}", std::process::id()));
+        render(&gateway(), &output).unwrap();
+
+        // Each module is declared once and backed by exactly one file, even
+        // for the `Auth` service next to the auth helpers.
+        let http = output.join("src/http");
+        let modules: Vec<String> = fs::read_to_string(http.join("mod.rs"))
+            .unwrap()
+            .lines()
+            .filter_map(|l| l.strip_prefix("pub mod ")?.strip_suffix(';'))
+            .map(str::to_string)
+            .collect();
+        assert!(modules.contains(&"auth".to_string()));
+        assert!(modules.contains(&"support".to_string()));
+        for module in &modules {
+            assert_eq!(modules.iter().filter(|m| *m == module).count(), 1);
+            let file = http.join(format!("{}.rs", module)).exists();
+            let dir = http.join(module).join("mod.rs").exists();
+            assert!(file != dir, "module '{}' has no single file", module);
+        }
+        assert!(http.join("support/auth.rs").exists());
+        let auth = fs::read_to_string(http.join("auth/mod.rs")).unwrap();
+        assert!(auth.contains("use crate::http::support::{ error::map_grpc_error };"));
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_reject_service_named_support() {
+        let mut config = gateway();
+        config.spec.services[0].name = "Support".to_string();
+        let error = AxumScaffolder::scaffold(&config, Path::new("unused")).unwrap_err();
+        assert!(error.to_string().contains("http::support"));
+    }
+}
@@ -1,3 +1,3 @@
 pub mod axum;
 pub mod nestjs;
-pub mod spring;
\ No newline at end of file
+pub mod spring;
@@ -7,7 +7,10 @@ use tera::{Context, Tera, Value};
 
 use crate::{
     parser::Config,
-    scaffolder::{Scaffolder, filters},
+    scaffolder::{
+        Scaffolder,
+        filters::{self, to_capitalize},
+    },
 };
 
 static SPRING_TEMPLATES: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/spring");
@@ -16,11 +19,26 @@ pub struct SpringScaffolder;
 
 impl Scaffolder for SpringScaffolder {
     fn scaffold(config: &Config, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        for service in &config.spec.services {
+            if let Some(e) = service
+                .endpoints
+                .iter()
+                .find(|e| e.client_streaming || e.server_streaming)
+            {
+                return Err(format!(
+                    "Streaming RPC '{}.{}' is not supported by the spring scaffolder",
+                    service.name, e.rpc
+                )
+                .into());
+            }
+        }
+
         let mut tera = Tera::default();
         tera.register_filter("snake_case", filters::snake_case_filter);
         tera.register_filter("camel_case", filters::camel_case_filter);
         tera.register_filter("capitalize", filters::capitalize_filter);
         tera.register_filter("lower_case", filters::lower_case_filter);
+        tera.register_filter("doc_comment", filters::doc_comment_filter);
 
         render_spring_templates(&mut tera, &SPRING_TEMPLATES, output, config)?;
         Ok(())
@@ -42,7 +60,11 @@ fn render_spring_templates(
             let content = std::str::from_utf8(file.contents())?;
             tera.add_raw_template(&name, content)?;
 
-            if name.contains("controller") || name.contains("client") || name.contains("service") {
+            if name.contains("controller")
+                || name.contains("client")
+                || name.contains("service")
+                || name.contains("dto")
+            {
                 for service in &config.spec.services {
                     let mut patched_service = serde_json::to_value(service)?.clone();
 
@@ -78,10 +100,16 @@ fn render_spring_templates(
                                 "service.java.tera",
                                 &format!("{}Service.java", service.name),
                             )
-                            .replace("client.java.tera", &format!("{}Client.java", service.name));
+                            .replace("client.java.tera", &format!("{}Client.java", service.name))
+                            .replace(
+                                "dto.java.tera",
+                                &format!("{}Dto.java", to_capitalize(&service.name)),
+                            );
 
                         if name.contains("controller") {
                             format!("controller/{}", file_stem)
+                        } else if name.contains("dto") {
+                            format!("dto/{}", file_stem)
                         } else if name.contains("service") || name.contains("client") {
                             format!("service/{}", file_stem)
                         } else {
@@ -129,4 +157,4 @@ fn render_spring_templates(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -3,7 +3,7 @@ use std::path::Path;
 use crate::parser::Config;
 
 pub mod factory;
-mod filters;
+pub(crate) mod filters;
 mod frameworks;
 
 pub trait Scaffolder {
@@ -0,0 +1,15 @@
+use axum::http::{HeaderMap, StatusCode, header};
+use tonic::metadata::{Ascii, MetadataValue};
+
+/// The `Authorization` header of an endpoint that requires auth, to forward
+/// as gRPC metadata. Missing or non-ASCII credentials are rejected with 401.
+pub fn authorization(headers: &HeaderMap) -> Result<MetadataValue<Ascii>, (StatusCode, String)> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or((
+            StatusCode::UNAUTHORIZED,
+            "Missing Authorization header".to_string(),
+        ))
+}
@@ -0,0 +1,49 @@
+use axum::http::{HeaderMap, StatusCode};
+use serde::de::DeserializeOwned;
+use tonic::metadata::{Ascii, MetadataMap, MetadataValue};
+
+use crate::http::support::query;
+
+/// Parses header `name` for a request field, the same way as a query-string
+/// value. A missing header is `None`.
+pub fn parse<T: DeserializeOwned>(
+    headers: &HeaderMap,
+    name: &str,
+) -> Result<Option<T>, (StatusCode, String)> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| bad_request(name, "not visible ASCII"))?;
+    query::parse_value(value)
+        .map(Some)
+        .map_err(|e| bad_request(name, e))
+}
+
+/// Forwards every value of each `(header, key)` pair found in `headers` as
+/// gRPC metadata `key`.
+pub fn forward(
+    headers: &HeaderMap,
+    metadata: &mut MetadataMap,
+    names: &[(&str, &'static str)],
+) -> Result<(), (StatusCode, String)> {
+    for (header, key) in names {
+        for value in headers.get_all(*header) {
+            let value: MetadataValue<Ascii> = value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| bad_request(header, "not visible ASCII"))?;
+            metadata.append(*key, value);
+        }
+    }
+    Ok(())
+}
+
+fn bad_request(name: &str, reason: impl std::fmt::Display) -> (StatusCode, String) {
+    (
+        StatusCode::BAD_REQUEST,
+        format!("Invalid header '{}': {}", name, reason),
+    )
+}
@@ -0,0 +1,11 @@
+//! Helpers shared by the generated services, kept apart from the service
+//! modules so that no service name can collide with them. A gateway only
+//! uses the ones its endpoints need.
+#![allow(dead_code)]
+
+pub mod auth;
+pub mod error;
+pub mod headers;
+pub mod ndjson;
+pub mod query;
+pub mod wkt;
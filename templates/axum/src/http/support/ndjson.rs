@@ -0,0 +1,55 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use tokio_stream::{Stream, StreamExt};
+
+pub const CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Reads the messages of a client-streaming call from either a JSON array or
+/// newline-delimited JSON.
+pub fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<Vec<T>, (StatusCode, String)> {
+    let trimmed = body.trim_ascii();
+    if trimmed.starts_with(b"[") {
+        return serde_json::from_slice(trimmed).map_err(bad_request);
+    }
+
+    trimmed
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .map(|line| serde_json::from_slice(line).map_err(bad_request))
+        .collect()
+}
+
+/// Streams server messages back as chunked NDJSON. A gRPC error ends the
+/// stream with a final `{"error": ...}` line, since the status is already sent.
+pub fn stream_response<S, T, H>(stream: S) -> Response
+where
+    S: Stream<Item = Result<T, tonic::Status>> + Send + 'static,
+    H: From<T> + Serialize,
+{
+    let lines = stream.map(|item| {
+        let value = match item {
+            Ok(message) => serde_json::to_value(H::from(message)).unwrap_or_default(),
+            Err(status) => json!({
+                "error": { "code": status.code() as i32, "message": status.message() }
+            }),
+        };
+        let mut line = value.to_string().into_bytes();
+        line.push(b'\n');
+        Ok::<_, std::convert::Infallible>(Bytes::from(line))
+    });
+
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+fn bad_request(e: serde_json::Error) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, format!("Invalid request body: {}", e))
+}
@@ -0,0 +1,20 @@
+use axum::http::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Parses the query-string value of `key`, read as JSON first so numbers and
+/// booleans keep their type, then as a plain string for strings and enums.
+pub fn parse<T: DeserializeOwned>(key: &str, value: &str) -> Result<T, (StatusCode, String)> {
+    parse_value(value).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid query parameter '{}': {}", key, e),
+        )
+    })
+}
+
+/// Parses a textual value the way [`parse`] does, for other sources such as
+/// headers.
+pub fn parse_value<T: DeserializeOwned>(value: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_str(value).or_else(|_| serde_json::from_value(Value::String(value.to_string())))
+}
@@ -0,0 +1,278 @@
+//! HTTP forms of the `google.protobuf` well-known types, serialized with
+//! their canonical JSON mapping. Wrapper types need no newtype: prost already
+//! maps them to `Option<T>`.
+
+use std::collections::BTreeMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use serde_json::{Map, Number, Value as Json};
+
+/// Serializes through `Display` and deserializes through `FromStr`.
+macro_rules! string_form {
+    ($name:ident, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct $name(pub prost_types::$name);
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map($name).map_err(de::Error::custom)
+            }
+        }
+
+        impl From<prost_types::$name> for $name {
+            fn from(value: prost_types::$name) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for prost_types::$name {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+string_form!(Timestamp, "`google.protobuf.Timestamp` as an RFC 3339 string.");
+string_form!(Duration, "`google.protobuf.Duration` as seconds with an `s` suffix, e.g. `1.5s`.");
+
+/// `google.protobuf.Empty` as `{}`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Empty {}
+
+impl From<()> for Empty {
+    fn from(_: ()) -> Self {
+        Empty {}
+    }
+}
+
+impl From<Empty> for () {
+    fn from(_: Empty) -> Self {}
+}
+
+/// `google.protobuf.FieldMask` as comma-separated lowerCamelCase paths.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldMask(pub prost_types::FieldMask);
+
+impl Serialize for FieldMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let paths: Vec<String> = self.0.paths.iter().map(|p| camel_case(p)).collect();
+        serializer.serialize_str(&paths.join(","))
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let paths = value
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .map(snake_case)
+            .collect();
+        Ok(FieldMask(prost_types::FieldMask { paths }))
+    }
+}
+
+impl From<prost_types::FieldMask> for FieldMask {
+    fn from(value: prost_types::FieldMask) -> Self {
+        FieldMask(value)
+    }
+}
+
+impl From<FieldMask> for prost_types::FieldMask {
+    fn from(value: FieldMask) -> Self {
+        value.0
+    }
+}
+
+fn camel_case(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut upper = false;
+    for c in path.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn snake_case(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `google.protobuf.Any` as `{"@type": ..., "value": <base64>}`. The gateway
+/// has no type registry, so the payload stays in its binary encoding.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Any(pub prost_types::Any);
+
+#[derive(Deserialize, Serialize)]
+struct AnyJson {
+    #[serde(rename = "@type")]
+    type_url: String,
+    #[serde(default)]
+    value: String,
+}
+
+impl Serialize for Any {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AnyJson {
+            type_url: self.0.type_url.clone(),
+            value: STANDARD.encode(&self.0.value),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Any {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = AnyJson::deserialize(deserializer)?;
+        let value = STANDARD.decode(json.value).map_err(de::Error::custom)?;
+        Ok(Any(prost_types::Any {
+            type_url: json.type_url,
+            value,
+        }))
+    }
+}
+
+impl From<prost_types::Any> for Any {
+    fn from(value: prost_types::Any) -> Self {
+        Any(value)
+    }
+}
+
+impl From<Any> for prost_types::Any {
+    fn from(value: Any) -> Self {
+        value.0
+    }
+}
+
+/// `google.protobuf.NullValue`, always `null`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct NullValue(());
+
+impl From<i32> for NullValue {
+    fn from(_: i32) -> Self {
+        NullValue(())
+    }
+}
+
+impl From<NullValue> for i32 {
+    fn from(_: NullValue) -> Self {
+        prost_types::NullValue::NullValue as i32
+    }
+}
+
+/// `google.protobuf.Value` as any JSON value.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Value(pub Json);
+
+/// `google.protobuf.Struct` as a JSON object.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Struct(pub Map<String, Json>);
+
+/// `google.protobuf.ListValue` as a JSON array.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ListValue(pub Vec<Json>);
+
+impl From<prost_types::Value> for Value {
+    fn from(value: prost_types::Value) -> Self {
+        Value(to_json(value))
+    }
+}
+
+impl From<Value> for prost_types::Value {
+    fn from(value: Value) -> Self {
+        from_json(value.0)
+    }
+}
+
+impl From<prost_types::Struct> for Struct {
+    fn from(value: prost_types::Struct) -> Self {
+        Struct(value.fields.into_iter().map(|(k, v)| (k, to_json(v))).collect())
+    }
+}
+
+impl From<Struct> for prost_types::Struct {
+    fn from(value: Struct) -> Self {
+        prost_types::Struct {
+            fields: value.0.into_iter().map(|(k, v)| (k, from_json(v))).collect(),
+        }
+    }
+}
+
+impl From<prost_types::ListValue> for ListValue {
+    fn from(value: prost_types::ListValue) -> Self {
+        ListValue(value.values.into_iter().map(to_json).collect())
+    }
+}
+
+impl From<ListValue> for prost_types::ListValue {
+    fn from(value: ListValue) -> Self {
+        prost_types::ListValue {
+            values: value.0.into_iter().map(from_json).collect(),
+        }
+    }
+}
+
+fn to_json(value: prost_types::Value) -> Json {
+    use prost_types::value::Kind;
+
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => Json::Null,
+        Some(Kind::BoolValue(b)) => Json::Bool(b),
+        Some(Kind::NumberValue(n)) => Number::from_f64(n).map_or(Json::Null, Json::Number),
+        Some(Kind::StringValue(s)) => Json::String(s),
+        Some(Kind::ListValue(list)) => Json::Array(list.values.into_iter().map(to_json).collect()),
+        Some(Kind::StructValue(object)) => Json::Object(
+            object
+                .fields
+                .into_iter()
+                .map(|(k, v)| (k, to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_json(value: Json) -> prost_types::Value {
+    use prost_types::value::Kind;
+
+    let kind = match value {
+        Json::Null => Kind::NullValue(prost_types::NullValue::NullValue as i32),
+        Json::Bool(b) => Kind::BoolValue(b),
+        Json::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        Json::String(s) => Kind::StringValue(s),
+        Json::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.into_iter().map(from_json).collect(),
+        }),
+        Json::Object(fields) => Kind::StructValue(prost_types::Struct {
+            fields: fields
+                .into_iter()
+                .map(|(k, v)| (k, from_json(v)))
+                .collect::<BTreeMap<_, _>>(),
+        }),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
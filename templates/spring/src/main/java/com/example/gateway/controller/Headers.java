@@ -0,0 +1,54 @@
+package com.example.gateway.controller;
+
+import java.util.function.Consumer;
+import java.util.function.Function;
+
+import io.grpc.Metadata;
+import org.springframework.http.HttpHeaders;
+import org.springframework.http.HttpStatus;
+import org.springframework.web.server.ResponseStatusException;
+
+/**
+ * Reads incoming headers into request fields and outgoing gRPC metadata.
+ */
+final class Headers {
+
+    private Headers() {
+    }
+
+    /**
+     * Hands header {@code name}, parsed with {@code parse}, to {@code set}
+     * when it is present. A value that does not parse is answered with 400
+     * Bad Request.
+     */
+    static <T> void bind(
+        HttpHeaders headers,
+        String name,
+        Function<String, T> parse,
+        Consumer<T> set) {
+        String value = headers.getFirst(name);
+        if (value == null) {
+            return;
+        }
+        T parsed;
+        try {
+            parsed = parse.apply(value);
+        } catch (IllegalArgumentException e) {
+            throw new ResponseStatusException(
+                HttpStatus.BAD_REQUEST,
+                "Invalid value for header '" + name + "': " + value);
+        }
+        set.accept(parsed);
+    }
+
+    /**
+     * Adds every value of header {@code name} to {@code metadata} as
+     * {@code key}.
+     */
+    static void forward(HttpHeaders headers, String name, String key, Metadata metadata) {
+        Metadata.Key<String> metadataKey = Metadata.Key.of(key, Metadata.ASCII_STRING_MARSHALLER);
+        for (String value : headers.getOrEmpty(name)) {
+            metadata.put(metadataKey, value);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+package com.example.gateway.controller;
+
+import java.util.List;
+import java.util.function.Consumer;
+import java.util.function.Function;
+
+import org.springframework.http.HttpStatus;
+import org.springframework.util.MultiValueMap;
+import org.springframework.web.server.ResponseStatusException;
+
+/**
+ * Binds query-string parameters to the fields of a request message.
+ */
+final class QueryParams {
+
+    private QueryParams() {
+    }
+
+    /**
+     * Hands every value given for {@code name} or {@code jsonName} to
+     * {@code set}, so that repeated fields take all of them. A value that
+     * does not parse is answered with 400 Bad Request.
+     */
+    static <T> void bind(
+        MultiValueMap<String, String> query,
+        String name,
+        String jsonName,
+        Function<String, T> parse,
+        Consumer<T> set) {
+        List<String> keys = jsonName == null ? List.of(name) : List.of(name, jsonName);
+        for (String key : keys) {
+            for (String value : query.getOrDefault(key, List.of())) {
+                T parsed;
+                try {
+                    parsed = parse.apply(value);
+                } catch (IllegalArgumentException e) {
+                    throw new ResponseStatusException(
+                        HttpStatus.BAD_REQUEST,
+                        "Invalid value for query parameter '" + key + "': " + value);
+                }
+                set.accept(parsed);
+            }
+        }
+    }
+
+    /**
+     * Unlike {@link Boolean#parseBoolean}, rejects anything but
+     * {@code true} and {@code false}.
+     */
+    static boolean parseBoolean(String value) {
+        return switch (value) {
+            case "true" -> true;
+            case "false" -> false;
+            default -> throw new IllegalArgumentException(value);
+        };
+    }
+}
@@ -0,0 +1,375 @@
+package com.example.gateway.dto;
+
+import java.math.BigDecimal;
+import java.time.Instant;
+import java.time.format.DateTimeParseException;
+import java.util.Arrays;
+import java.util.Base64;
+import java.util.Map;
+import java.util.stream.Collectors;
+
+import com.fasterxml.jackson.annotation.JsonCreator;
+import com.fasterxml.jackson.annotation.JsonProperty;
+import com.fasterxml.jackson.annotation.JsonValue;
+import com.fasterxml.jackson.databind.JsonNode;
+import com.fasterxml.jackson.databind.node.JsonNodeFactory;
+import com.fasterxml.jackson.databind.node.ObjectNode;
+
+/**
+ * HTTP forms of the google.protobuf well-known types, serialized with their
+ * canonical JSON mapping.
+ */
+public final class WellKnown {
+
+    private WellKnown() {
+    }
+
+    public record Timestamp(@JsonValue String value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Timestamp {
+            try {
+                Instant.parse(value);
+            } catch (DateTimeParseException e) {
+                throw new IllegalArgumentException("Invalid RFC 3339 timestamp: " + value, e);
+            }
+        }
+
+        public com.google.protobuf.Timestamp toProto() {
+            Instant instant = Instant.parse(value);
+            return com.google.protobuf.Timestamp.newBuilder()
+                .setSeconds(instant.getEpochSecond())
+                .setNanos(instant.getNano())
+                .build();
+        }
+
+        public static Timestamp fromProto(com.google.protobuf.Timestamp value) {
+            return new Timestamp(Instant.ofEpochSecond(value.getSeconds(), value.getNanos()).toString());
+        }
+    }
+
+    public record Duration(@JsonValue String value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Duration {
+            if (value == null || !value.endsWith("s")) {
+                throw new IllegalArgumentException("Invalid duration: " + value);
+            }
+            seconds(value);
+        }
+
+        private static BigDecimal seconds(String value) {
+            try {
+                return new BigDecimal(value.substring(0, value.length() - 1));
+            } catch (NumberFormatException e) {
+                throw new IllegalArgumentException("Invalid duration: " + value, e);
+            }
+        }
+
+        public com.google.protobuf.Duration toProto() {
+            BigDecimal seconds = seconds(value);
+            return com.google.protobuf.Duration.newBuilder()
+                .setSeconds(seconds.longValue())
+                .setNanos(seconds.remainder(BigDecimal.ONE).movePointRight(9).intValue())
+                .build();
+        }
+
+        public static Duration fromProto(com.google.protobuf.Duration value) {
+            BigDecimal seconds = BigDecimal.valueOf(value.getSeconds())
+                .add(BigDecimal.valueOf(value.getNanos(), 9));
+            return new Duration(seconds.stripTrailingZeros().toPlainString() + "s");
+        }
+    }
+
+    public record Empty() {
+        @JsonCreator
+        public static Empty of(Map<String, Object> ignored) {
+            return new Empty();
+        }
+
+        @JsonValue
+        public Map<String, Object> json() {
+            return Map.of();
+        }
+
+        public com.google.protobuf.Empty toProto() {
+            return com.google.protobuf.Empty.getDefaultInstance();
+        }
+
+        public static Empty fromProto(com.google.protobuf.Empty value) {
+            return new Empty();
+        }
+    }
+
+    public record FieldMask(@JsonValue String value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public FieldMask {
+        }
+
+        public com.google.protobuf.FieldMask toProto() {
+            var builder = com.google.protobuf.FieldMask.newBuilder();
+            Arrays.stream(value.split(","))
+                .filter(path -> !path.isEmpty())
+                .map(path -> path.replaceAll("([A-Z])", "_$1").toLowerCase())
+                .forEach(builder::addPaths);
+            return builder.build();
+        }
+
+        public static FieldMask fromProto(com.google.protobuf.FieldMask value) {
+            return new FieldMask(value.getPathsList().stream()
+                .map(WellKnown::camelCase)
+                .collect(Collectors.joining(",")));
+        }
+    }
+
+    private static String camelCase(String path) {
+        StringBuilder out = new StringBuilder();
+        boolean upper = false;
+        for (char c : path.toCharArray()) {
+            if (c == '_') {
+                upper = true;
+            } else {
+                out.append(upper ? Character.toUpperCase(c) : c);
+                upper = false;
+            }
+        }
+        return out.toString();
+    }
+
+    /** The gateway has no type registry, so the payload stays in its binary encoding. */
+    public record Any(@JsonProperty("@type") String type, String value) {
+        public com.google.protobuf.Any toProto() {
+            return com.google.protobuf.Any.newBuilder()
+                .setTypeUrl(type)
+                .setValue(com.google.protobuf.ByteString.copyFrom(Base64.getDecoder().decode(value == null ? "" : value)))
+                .build();
+        }
+
+        public static Any fromProto(com.google.protobuf.Any value) {
+            return new Any(value.getTypeUrl(), Base64.getEncoder().encodeToString(value.getValue().toByteArray()));
+        }
+    }
+
+    public record NullValue() {
+        @JsonValue
+        public Object json() {
+            return null;
+        }
+
+        public com.google.protobuf.NullValue toProto() {
+            return com.google.protobuf.NullValue.NULL_VALUE;
+        }
+
+        public static NullValue fromProto(com.google.protobuf.NullValue value) {
+            return new NullValue();
+        }
+    }
+
+    public record Value(@JsonValue JsonNode value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Value {
+        }
+
+        public com.google.protobuf.Value toProto() {
+            return toValue(value);
+        }
+
+        public static Value fromProto(com.google.protobuf.Value value) {
+            return new Value(fromValue(value));
+        }
+    }
+
+    public record Struct(@JsonValue ObjectNode value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Struct {
+        }
+
+        public com.google.protobuf.Struct toProto() {
+            return toValue(value).getStructValue();
+        }
+
+        public static Struct fromProto(com.google.protobuf.Struct value) {
+            return new Struct((ObjectNode) fromValue(com.google.protobuf.Value.newBuilder().setStructValue(value).build()));
+        }
+    }
+
+    public record ListValue(@JsonValue JsonNode value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public ListValue {
+        }
+
+        public com.google.protobuf.ListValue toProto() {
+            return toValue(value).getListValue();
+        }
+
+        public static ListValue fromProto(com.google.protobuf.ListValue value) {
+            return new ListValue(fromValue(com.google.protobuf.Value.newBuilder().setListValue(value).build()));
+        }
+    }
+
+    private static com.google.protobuf.Value toValue(JsonNode node) {
+        var builder = com.google.protobuf.Value.newBuilder();
+        if (node == null || node.isNull()) {
+            builder.setNullValue(com.google.protobuf.NullValue.NULL_VALUE);
+        } else if (node.isBoolean()) {
+            builder.setBoolValue(node.booleanValue());
+        } else if (node.isNumber()) {
+            builder.setNumberValue(node.doubleValue());
+        } else if (node.isTextual()) {
+            builder.setStringValue(node.textValue());
+        } else if (node.isArray()) {
+            var list = com.google.protobuf.ListValue.newBuilder();
+            node.forEach(item -> list.addValues(toValue(item)));
+            builder.setListValue(list);
+        } else {
+            var struct = com.google.protobuf.Struct.newBuilder();
+            node.fields().forEachRemaining(e -> struct.putFields(e.getKey(), toValue(e.getValue())));
+            builder.setStructValue(struct);
+        }
+        return builder.build();
+    }
+
+    private static JsonNode fromValue(com.google.protobuf.Value value) {
+        JsonNodeFactory factory = JsonNodeFactory.instance;
+        return switch (value.getKindCase()) {
+            case BOOL_VALUE -> factory.booleanNode(value.getBoolValue());
+            case NUMBER_VALUE -> factory.numberNode(value.getNumberValue());
+            case STRING_VALUE -> factory.textNode(value.getStringValue());
+            case LIST_VALUE -> {
+                var array = factory.arrayNode();
+                value.getListValue().getValuesList().forEach(item -> array.add(fromValue(item)));
+                yield array;
+            }
+            case STRUCT_VALUE -> {
+                var object = factory.objectNode();
+                value.getStructValue().getFieldsMap().forEach((k, v) -> object.set(k, fromValue(v)));
+                yield object;
+            }
+            default -> factory.nullNode();
+        };
+    }
+
+    public record BoolValue(@JsonValue Boolean value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public BoolValue {
+        }
+
+        public com.google.protobuf.BoolValue toProto() {
+            return com.google.protobuf.BoolValue.of(value);
+        }
+
+        public static BoolValue fromProto(com.google.protobuf.BoolValue value) {
+            return new BoolValue(value.getValue());
+        }
+    }
+
+    public record Int32Value(@JsonValue Integer value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Int32Value {
+        }
+
+        public com.google.protobuf.Int32Value toProto() {
+            return com.google.protobuf.Int32Value.of(value);
+        }
+
+        public static Int32Value fromProto(com.google.protobuf.Int32Value value) {
+            return new Int32Value(value.getValue());
+        }
+    }
+
+    public record UInt32Value(@JsonValue Integer value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public UInt32Value {
+        }
+
+        public com.google.protobuf.UInt32Value toProto() {
+            return com.google.protobuf.UInt32Value.of(value);
+        }
+
+        public static UInt32Value fromProto(com.google.protobuf.UInt32Value value) {
+            return new UInt32Value(value.getValue());
+        }
+    }
+
+    public record Int64Value(@JsonValue Long value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public Int64Value {
+        }
+
+        public com.google.protobuf.Int64Value toProto() {
+            return com.google.protobuf.Int64Value.of(value);
+        }
+
+        public static Int64Value fromProto(com.google.protobuf.Int64Value value) {
+            return new Int64Value(value.getValue());
+        }
+    }
+
+    public record UInt64Value(@JsonValue Long value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public UInt64Value {
+        }
+
+        public com.google.protobuf.UInt64Value toProto() {
+            return com.google.protobuf.UInt64Value.of(value);
+        }
+
+        public static UInt64Value fromProto(com.google.protobuf.UInt64Value value) {
+            return new UInt64Value(value.getValue());
+        }
+    }
+
+    public record FloatValue(@JsonValue Float value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public FloatValue {
+        }
+
+        public com.google.protobuf.FloatValue toProto() {
+            return com.google.protobuf.FloatValue.of(value);
+        }
+
+        public static FloatValue fromProto(com.google.protobuf.FloatValue value) {
+            return new FloatValue(value.getValue());
+        }
+    }
+
+    public record DoubleValue(@JsonValue Double value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public DoubleValue {
+        }
+
+        public com.google.protobuf.DoubleValue toProto() {
+            return com.google.protobuf.DoubleValue.of(value);
+        }
+
+        public static DoubleValue fromProto(com.google.protobuf.DoubleValue value) {
+            return new DoubleValue(value.getValue());
+        }
+    }
+
+    public record StringValue(@JsonValue String value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public StringValue {
+        }
+
+        public com.google.protobuf.StringValue toProto() {
+            return com.google.protobuf.StringValue.of(value);
+        }
+
+        public static StringValue fromProto(com.google.protobuf.StringValue value) {
+            return new StringValue(value.getValue());
+        }
+    }
+
+    public record BytesValue(@JsonValue byte[] value) {
+        @JsonCreator(mode = JsonCreator.Mode.DELEGATING)
+        public BytesValue {
+        }
+
+        public com.google.protobuf.BytesValue toProto() {
+            return com.google.protobuf.BytesValue.of(com.google.protobuf.ByteString.copyFrom(value));
+        }
+
+        public static BytesValue fromProto(com.google.protobuf.BytesValue value) {
+            return new BytesValue(value.getValue().toByteArray());
+        }
+    }
+}